@@ -1,5 +1,110 @@
-import {DevnetConfig} from "./src/types";
-import {ResolverCallback} from "./src/promise";
+import {AccountData, DevnetConfig} from "./src/types";
+import {Error} from "./src/error";
+import {AdminMethods} from "./src/admin";
+import {ContractClass, ReadArgs, StateUpdate, Transaction, TransactionReceipt} from "./src/read";
 
-export type ProviderCallback = (val: string) => void;
-export function createDevnetServer(callback: ResolverCallback<string[]>, config: DevnetConfig, provider: ProviderCallback): void;
+// Block as returned by starknet_getBlockWithTxs
+export interface Block {
+    block_hash: string;
+    block_number: number;
+    parent_hash: string;
+    timestamp: number;
+    transactions: unknown[];
+    [key: string]: unknown;
+}
+
+export interface SentBlock {
+    block_number: number;
+    block_hash: string;
+}
+
+export type ChainResetReason = 'restart' | 'load' | 'revert';
+
+// seq increases by one with every event of an instance, starting at 1
+export type FeedEvent = { seq: number } & (
+    | { type: 'NewBlock'; block: Block }
+    | { type: 'BlocksAborted'; blocks: SentBlock[] }
+    | { type: 'ChainReset'; reason: ChainResetReason; accounts: AccountData[] }
+    | { type: 'ServerStopped' }
+    // Replayed in place of a failure that was passed to the callback as its error
+    | { type: 'Failed'; message: string }
+    // Blocks shed while the feed queue was full, they are still available through replayFrom
+    | { type: 'Lagged'; skipped: number; first_skipped_seq: number; last_skipped_seq: number }
+);
+
+// Error-first, event is undefined when error is set
+export type ProviderCallback = (error: Error | undefined, event?: FeedEvent) => void;
+// Receives FeedEvent serialized to JSON, when feedFormat is 'json' (string) or 'buffer' (UTF-8 Buffer)
+export type RawProviderCallback = (error: Error | undefined, payload?: string | Buffer) => void;
+
+// Opaque handle of a running devnet
+export interface DevnetInstanceHandle {
+    readonly __devnetInstance: unique symbol;
+}
+
+export interface DevnetStartResult {
+    // Unique within the process, see getDevnetInstance
+    id: number;
+    // e.g. http://127.0.0.1:5050, loopback if the server listens on all interfaces. Undefined when headless
+    url?: string;
+    // Actually bound address, e.g. 127.0.0.1:5050. Undefined when headless
+    address?: string;
+    // Actually bound port, differs from the configured one if that was 0. Undefined when headless
+    port?: number;
+    accounts: AccountData[];
+    instance: DevnetInstanceHandle;
+}
+
+// Async functions reject with Error
+export function createDevnetServer(config: DevnetConfig, provider: ProviderCallback | RawProviderCallback): Promise<DevnetStartResult>;
+export function stopDevnetServer(instance: DevnetInstanceHandle): Promise<void>;
+export type InstanceStatus = 'running' | 'stopping';
+
+// Instance that was started and whose server hasn't exited yet
+export interface InstanceInfo {
+    id: number;
+    // Actually bound port, null when headless
+    port: number | null;
+    // As passed to createDevnetServer
+    config: DevnetConfig;
+    status: InstanceStatus;
+}
+
+export function listDevnetInstances(): InstanceInfo[];
+export function getDevnetInstance(id: number): DevnetStartResult | undefined;
+// Stops every listed instance, rejects with the first failure once all of them are done
+export function stopAllDevnetServers(): Promise<void>;
+// JSON-RPC call handled in-process, without HTTP. Resolves with the result, rejects with an Error carrying code and data
+export function request(instance: DevnetInstanceHandle, method: string, params?: unknown): Promise<unknown>;
+// Admin endpoint called without HTTP. Rejects with an Error carrying the HTTP status if the endpoint fails
+export function adminRequest<M extends keyof AdminMethods>(
+    instance: DevnetInstanceHandle,
+    method: M,
+    args?: AdminMethods[M]['args']
+): Promise<AdminMethods[M]['result']>;
+// Read-only query without HTTP. Rejects with an Error carrying the JSON-RPC code, e.g. for unknown hashes
+export function readRequest(instance: DevnetInstanceHandle, method: 'getBlock', args?: ReadArgs): Promise<Block>;
+export function readRequest(instance: DevnetInstanceHandle, method: 'getTransaction', args: ReadArgs): Promise<Transaction>;
+export function readRequest(instance: DevnetInstanceHandle, method: 'getTransactionReceipt', args: ReadArgs): Promise<TransactionReceipt>;
+export function readRequest(instance: DevnetInstanceHandle, method: 'getStateUpdate', args?: ReadArgs): Promise<StateUpdate>;
+export function readRequest(instance: DevnetInstanceHandle, method: 'getClass', args: ReadArgs): Promise<ContractClass>;
+export function readRequest(instance: DevnetInstanceHandle, method: 'getNonce' | 'getStorageAt', args: ReadArgs): Promise<string>;
+export interface SnapshotInfo {
+    // Unique within the instance
+    id: number;
+    // Latest block when the snapshot was taken
+    blockNumber?: number;
+}
+
+// Copies the devnet state in memory, kept until deleted or the instance is gone
+export function takeSnapshot(instance: DevnetInstanceHandle): Promise<SnapshotInfo>;
+// Swaps the state back to the snapshot. Emits ChainReset with reason 'revert', the snapshot is kept
+export function revertSnapshot(instance: DevnetInstanceHandle, id: number): Promise<void>;
+export function listSnapshots(instance: DevnetInstanceHandle): Promise<SnapshotInfo[]>;
+export function deleteSnapshot(instance: DevnetInstanceHandle, id: number): Promise<void>;
+// Number of data feed events queued for the JS thread that haven't been delivered yet
+export function getFeedQueueDepth(instance: DevnetInstanceHandle): number;
+// Sequence number of the last event sent by the instance, 0 if none was sent yet
+export function getFeedCursor(instance: DevnetInstanceHandle): number;
+// Retained events with seq >= the given one, always as objects regardless of feedFormat. Throws if some of them were already evicted
+export function replayFrom(instance: DevnetInstanceHandle, seq: number): FeedEvent[];
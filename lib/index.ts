@@ -1,14 +1,200 @@
-import { createDevnetServer, ProviderCallback } from './getAlpaca';
-import { createPromise } from './src/promise';
-import { DevnetConfig } from './src/types';
+import {
+    adminRequest,
+    createDevnetServer,
+    deleteSnapshot,
+    getDevnetInstance,
+    getFeedCursor,
+    getFeedQueueDepth,
+    listDevnetInstances,
+    listSnapshots,
+    readRequest,
+    replayFrom,
+    request,
+    revertSnapshot,
+    stopAllDevnetServers,
+    stopDevnetServer,
+    takeSnapshot,
+    DevnetInstanceHandle,
+    DevnetStartResult,
+    FeedEvent,
+    InstanceInfo,
+    ProviderCallback,
+    RawProviderCallback,
+} from './getAlpaca';
+import {
+    AbortBlocksArgs,
+    AccountBalanceArgs,
+    DumpArgs,
+    IncreaseTimeArgs,
+    LoadArgs,
+    MintArgs,
+    SetTimeArgs,
+} from './src/admin';
+import { BlockId } from './src/read';
+import { AccountData, DevnetConfig } from './src/types';
 
+export type {
+    Block,
+    ChainResetReason,
+    FeedEvent,
+    InstanceInfo,
+    InstanceStatus,
+    ProviderCallback,
+    RawProviderCallback,
+    SentBlock,
+    SnapshotInfo,
+} from './getAlpaca';
+export * from './src/admin';
+export * from './src/read';
 export * from './src/types';
-export * from './src/promise';
 export * from './src/error'
 
 export class Devnet {
-    // TODO: proper return type
-    static start(config: DevnetConfig, provider: ProviderCallback): Promise<string[]> {
-        return createPromise(createDevnetServer, config, provider);
+    readonly id: number;
+    // Undefined when headless
+    readonly url?: string;
+    readonly address?: string;
+    readonly port?: number;
+    readonly accounts: AccountData[];
+    private readonly instance: DevnetInstanceHandle;
+
+    private constructor({ id, url, address, port, accounts, instance }: DevnetStartResult) {
+        this.id = id;
+        this.url = url;
+        this.address = address;
+        this.port = port;
+        this.accounts = accounts;
+        this.instance = instance;
+    }
+
+    static start(config: DevnetConfig, provider: ProviderCallback): Promise<Devnet>;
+    // For feedFormat 'json' and 'buffer'
+    static start(config: DevnetConfig, provider: RawProviderCallback): Promise<Devnet>;
+    static async start(config: DevnetConfig, provider: ProviderCallback | RawProviderCallback): Promise<Devnet> {
+        return new Devnet(await createDevnetServer(config, provider));
+    }
+
+    // Instances of this process that were started and haven't exited yet
+    static list(): InstanceInfo[] {
+        return listDevnetInstances();
+    }
+
+    static get(id: number): Devnet | undefined {
+        const result = getDevnetInstance(id);
+        return result && new Devnet(result);
+    }
+
+    static stopAll(): Promise<void> {
+        return stopAllDevnetServers();
+    }
+
+    get feedQueueDepth(): number {
+        return getFeedQueueDepth(this.instance);
+    }
+
+    getFeedCursor(): number {
+        return getFeedCursor(this.instance);
+    }
+
+    replayFrom(seq: number): FeedEvent[] {
+        return replayFrom(this.instance, seq);
+    }
+
+    // Same as a call to the /rpc endpoint, but without going through HTTP
+    request<T = unknown>(method: string, params?: unknown): Promise<T> {
+        return request(this.instance, method, params) as Promise<T>;
+    }
+
+    getBlock(blockId?: BlockId) {
+        return readRequest(this.instance, 'getBlock', { blockId });
+    }
+
+    getTransaction(transactionHash: string) {
+        return readRequest(this.instance, 'getTransaction', { transactionHash });
+    }
+
+    getTransactionReceipt(transactionHash: string) {
+        return readRequest(this.instance, 'getTransactionReceipt', { transactionHash });
+    }
+
+    getStateUpdate(blockId?: BlockId) {
+        return readRequest(this.instance, 'getStateUpdate', { blockId });
+    }
+
+    getClass(classHash: string, blockId?: BlockId) {
+        return readRequest(this.instance, 'getClass', { classHash, blockId });
+    }
+
+    getNonce(contractAddress: string, blockId?: BlockId) {
+        return readRequest(this.instance, 'getNonce', { contractAddress, blockId });
+    }
+
+    getStorageAt(contractAddress: string, key: string, blockId?: BlockId) {
+        return readRequest(this.instance, 'getStorageAt', { contractAddress, key, blockId });
+    }
+
+    mint(args: MintArgs) {
+        return adminRequest(this.instance, 'mint', { ...args, amount: args.amount.toString() });
+    }
+
+    setTime(args: SetTimeArgs) {
+        return adminRequest(this.instance, 'setTime', args);
+    }
+
+    increaseTime(args: IncreaseTimeArgs) {
+        return adminRequest(this.instance, 'increaseTime', args);
+    }
+
+    createBlock() {
+        return adminRequest(this.instance, 'createBlock');
+    }
+
+    abortBlocks(args: AbortBlocksArgs) {
+        return adminRequest(this.instance, 'abortBlocks', args);
+    }
+
+    restart() {
+        return adminRequest(this.instance, 'restart');
+    }
+
+    dump(args?: DumpArgs) {
+        return adminRequest(this.instance, 'dump', args);
+    }
+
+    load(args: LoadArgs) {
+        return adminRequest(this.instance, 'load', args);
+    }
+
+    accountBalance(args: AccountBalanceArgs) {
+        return adminRequest(this.instance, 'accountBalance', args);
+    }
+
+    feeToken() {
+        return adminRequest(this.instance, 'feeToken');
+    }
+
+    forkStatus() {
+        return adminRequest(this.instance, 'forkStatus');
+    }
+
+    snapshot() {
+        return takeSnapshot(this.instance);
+    }
+
+    revert(id: number) {
+        return revertSnapshot(this.instance, id);
+    }
+
+    listSnapshots() {
+        return listSnapshots(this.instance);
+    }
+
+    deleteSnapshot(id: number) {
+        return deleteSnapshot(this.instance, id);
+    }
+
+    // Resolves once the server is shut down and its port is released
+    stop(): Promise<void> {
+        return stopDevnetServer(this.instance);
     }
 }
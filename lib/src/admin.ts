@@ -0,0 +1,92 @@
+export type BalanceUnit = 'WEI' | 'FRI';
+
+export interface MintArgs {
+    address: string;
+    // Decimal or 0x-prefixed hex string, since amounts go beyond Number.MAX_SAFE_INTEGER
+    amount: string | bigint;
+    unit?: BalanceUnit;
+}
+
+export interface MintResult {
+    new_balance: string;
+    unit: BalanceUnit;
+    tx_hash: string;
+}
+
+export interface SetTimeArgs {
+    // Unix timestamp in seconds
+    time: number;
+    generate_block?: boolean;
+}
+
+export interface SetTimeResult {
+    block_timestamp: number;
+    block_hash?: string;
+}
+
+export interface IncreaseTimeArgs {
+    // Seconds
+    time: number;
+}
+
+export interface IncreaseTimeResult {
+    timestamp_increased_by: number;
+    block_hash: string;
+}
+
+export interface CreateBlockResult {
+    block_hash: string;
+}
+
+export interface AbortBlocksArgs {
+    starting_block_hash: string;
+}
+
+export interface AbortBlocksResult {
+    aborted: string[];
+}
+
+export interface DumpArgs {
+    // Falls back to dumpPath of the config
+    path?: string;
+}
+
+export interface LoadArgs {
+    path: string;
+}
+
+export interface AccountBalanceArgs {
+    address: string;
+    unit?: BalanceUnit;
+    block_tag?: 'latest' | 'pending';
+}
+
+export interface AccountBalanceResult {
+    amount: string;
+    unit: BalanceUnit;
+}
+
+export interface FeeTokenResult {
+    symbol: string;
+    address: string;
+}
+
+export interface ForkStatusResult {
+    url?: string;
+    block?: number;
+}
+
+// Arguments and result of every admin endpoint, by method name
+export interface AdminMethods {
+    mint: { args: MintArgs; result: MintResult };
+    setTime: { args: SetTimeArgs; result: SetTimeResult };
+    increaseTime: { args: IncreaseTimeArgs; result: IncreaseTimeResult };
+    createBlock: { args: undefined; result: CreateBlockResult };
+    abortBlocks: { args: AbortBlocksArgs; result: AbortBlocksResult };
+    restart: { args: undefined; result: unknown };
+    dump: { args: DumpArgs | undefined; result: unknown };
+    load: { args: LoadArgs; result: unknown };
+    accountBalance: { args: AccountBalanceArgs; result: AccountBalanceResult };
+    feeToken: { args: undefined; result: FeeTokenResult };
+    forkStatus: { args: undefined; result: ForkStatusResult };
+}
@@ -1,6 +1,19 @@
 export enum ErrorType {
     InternalError,
-    DevnetError
+    DevnetError,
+    ConfigError,
+    // Bad arguments passed to an addon function, e.g. an invalid instance handle or method name
+    ArgumentError
+}
+
+export interface InvalidField {
+    field: string;
+    message: string;
+}
+
+export interface PortAttempt {
+    port: number;
+    message: string;
 }
 
 export interface Error {
@@ -8,4 +21,15 @@ export interface Error {
     engine: string;
     message: string;
     backtrace?: string;
+    // Set for ErrorType.ConfigError, lists every invalid config field
+    fields?: InvalidField[];
+    // Set for JSON-RPC failures
+    code?: number;
+    data?: unknown;
+    // Set for admin API failures, the HTTP status the endpoint responded with
+    status?: number;
+    // Set when none of the ports allowed by autoPort could be bound, in the order they were tried
+    attempts?: PortAttempt[];
+    // Set for data feed failures, sequenced along with the feed events
+    seq?: number;
 }
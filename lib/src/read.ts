@@ -0,0 +1,87 @@
+export type BlockTag = 'latest' | 'pending';
+export type BlockId = BlockTag | { block_number: number } | { block_hash: string };
+
+// As returned by starknet_getTransactionByHash, fields depend on type and version
+export interface Transaction {
+    transaction_hash: string;
+    type: 'INVOKE' | 'DECLARE' | 'DEPLOY_ACCOUNT' | 'DEPLOY' | 'L1_HANDLER';
+    version: string;
+    [key: string]: unknown;
+}
+
+export interface FeePayment {
+    amount: string;
+    unit: 'WEI' | 'FRI';
+}
+
+export interface Event {
+    from_address: string;
+    keys: string[];
+    data: string[];
+}
+
+export interface MessageToL1 {
+    from_address: string;
+    to_address: string;
+    payload: string[];
+}
+
+// As returned by starknet_getTransactionReceipt, block fields are missing for pending transactions
+export interface TransactionReceipt {
+    transaction_hash: string;
+    type: Transaction['type'];
+    actual_fee: FeePayment | string;
+    execution_status: 'SUCCEEDED' | 'REVERTED';
+    finality_status: 'ACCEPTED_ON_L2' | 'ACCEPTED_ON_L1';
+    block_hash?: string;
+    block_number?: number;
+    messages_sent: MessageToL1[];
+    events: Event[];
+    revert_reason?: string;
+    [key: string]: unknown;
+}
+
+export interface StorageEntry {
+    key: string;
+    value: string;
+}
+
+export interface StateDiff {
+    storage_diffs: { address: string; storage_entries: StorageEntry[] }[];
+    deprecated_declared_classes: string[];
+    declared_classes: { class_hash: string; compiled_class_hash: string }[];
+    deployed_contracts: { address: string; class_hash: string }[];
+    replaced_classes: { contract_address: string; class_hash: string }[];
+    nonces: { contract_address: string; nonce: string }[];
+}
+
+// As returned by starknet_getStateUpdate, block_hash and new_root are missing for the pending block
+export interface StateUpdate {
+    block_hash?: string;
+    new_root?: string;
+    old_root: string;
+    state_diff: StateDiff;
+}
+
+// Sierra class, or a Cairo 0 one with a compressed program
+export type ContractClass =
+    | {
+          sierra_program: string[];
+          contract_class_version: string;
+          entry_points_by_type: unknown;
+          abi: string;
+      }
+    | {
+          program: string;
+          entry_points_by_type: unknown;
+          abi?: unknown[];
+      };
+
+// Arguments of the read methods, blockId defaults to 'latest'
+export interface ReadArgs {
+    blockId?: BlockId;
+    transactionHash?: string;
+    classHash?: string;
+    contractAddress?: string;
+    key?: string;
+}
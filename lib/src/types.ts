@@ -1,5 +1,47 @@
+export type ChainId = 'MAINNET' | 'TESTNET';
+export type DumpOn = 'exit' | 'transaction';
+export type FeedOverflow = 'drop' | 'coalesce';
+export type FeedFormat = 'object' | 'json' | 'buffer';
+
 export interface DevnetConfig {
     seed: number,
+    // 0 lets the OS pick a free port, see Devnet.port. Ignored when headless
     port: number,
-    totalAccounts: number
-}
\ No newline at end of file
+    // No HTTP server is started, the devnet is only reachable through Devnet.request
+    headless?: boolean,
+    // If port is busy, up to this many successive ports are tried. Devnet.port tells the one bound
+    autoPort?: number,
+    totalAccounts: number,
+    // Optional fields fall back to devnet defaults
+    host?: string,
+    chainId?: ChainId,
+    // Decimal or 0x-prefixed hex string, in WEI
+    initialBalance?: string,
+    gasPrice?: number,
+    // Unix timestamp in seconds
+    startTime?: number,
+    // Request timeout in seconds
+    requestTimeout?: number,
+    // Transactions stay pending until Devnet.createBlock, instead of each getting its own block
+    blocksOnDemand?: boolean,
+    // Path to a Cairo 1 Sierra artifact used as the predeclared account class
+    accountClassPath?: string,
+    // 'exit' dumps when the instance is stopped, including on process exit, which waits up to 5 seconds for it
+    dumpOn?: DumpOn,
+    dumpPath?: string,
+    // Max number of undelivered feed events, blocks beyond it are shed. Unbounded by default
+    feedQueueLimit?: number,
+    // 'drop' skips shed blocks, 'coalesce' still delivers the latest one. Defaults to 'drop'
+    feedOverflow?: FeedOverflow,
+    // 'json' and 'buffer' serialize events off the JS thread, ready to be forwarded. Defaults to 'object'
+    feedFormat?: FeedFormat,
+    // Number of most recent events kept for replayFrom, 0 disables replay. Defaults to 1024
+    feedReplayCapacity?: number
+}
+
+export interface AccountData {
+    account_address: string,
+    public_key: string,
+    private_key: string,
+    balance: string
+}
@@ -0,0 +1,243 @@
+use axum::{
+    extract::{Extension, Json, Query},
+    response::IntoResponse,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use snafu::OptionExt;
+use starknet_devnet_server::api::http::{endpoints as http, HttpApiHandler};
+use std::fmt::Display;
+
+use crate::data_feed::{ChainResetReason, DataFeed};
+#[cfg(test)]
+use crate::errors::Error;
+use crate::errors::{HttpApiSnafu, InvalidArgumentValueSnafu, Result};
+
+/// Devnet admin endpoints that can be called in-process
+#[derive(Clone, Copy)]
+pub enum AdminMethod {
+    Mint,
+    SetTime,
+    IncreaseTime,
+    CreateBlock,
+    AbortBlocks,
+    Restart,
+    Dump,
+    Load,
+    AccountBalance,
+    FeeToken,
+    ForkStatus,
+}
+
+/// How the data feed catches up after a call, same as for the HTTP routes
+enum FeedUpdate {
+    None,
+    Sync,
+    Reset(ChainResetReason),
+}
+
+impl AdminMethod {
+    pub fn parse(method: &str) -> Option<Self> {
+        match method {
+            "mint" => Some(AdminMethod::Mint),
+            "setTime" => Some(AdminMethod::SetTime),
+            "increaseTime" => Some(AdminMethod::IncreaseTime),
+            "createBlock" => Some(AdminMethod::CreateBlock),
+            "abortBlocks" => Some(AdminMethod::AbortBlocks),
+            "restart" => Some(AdminMethod::Restart),
+            "dump" => Some(AdminMethod::Dump),
+            "load" => Some(AdminMethod::Load),
+            "accountBalance" => Some(AdminMethod::AccountBalance),
+            "feeToken" => Some(AdminMethod::FeeToken),
+            "forkStatus" => Some(AdminMethod::ForkStatus),
+            _ => None,
+        }
+    }
+
+    fn feed_update(self) -> FeedUpdate {
+        match self {
+            AdminMethod::Mint
+            | AdminMethod::SetTime
+            | AdminMethod::IncreaseTime
+            | AdminMethod::CreateBlock
+            | AdminMethod::AbortBlocks => FeedUpdate::Sync,
+            AdminMethod::Restart => FeedUpdate::Reset(ChainResetReason::Restart),
+            AdminMethod::Load => FeedUpdate::Reset(ChainResetReason::Load),
+            AdminMethod::Dump | AdminMethod::AccountBalance | AdminMethod::FeeToken | AdminMethod::ForkStatus => {
+                FeedUpdate::None
+            }
+        }
+    }
+}
+
+/// Calls the endpoint handlers of the HTTP routes directly, with the arguments deserialized into their request models
+#[derive(Clone)]
+pub struct AdminApi {
+    http_handler: HttpApiHandler,
+    data_feed: DataFeed,
+}
+
+impl AdminApi {
+    pub fn new(http_handler: HttpApiHandler, data_feed: DataFeed) -> Self {
+        Self {
+            http_handler,
+            data_feed,
+        }
+    }
+
+    /// `args` holds the fields of the request model, i.e. what the endpoint takes as JSON body or query over HTTP.
+    /// Resolves with the response model as JSON, or null if the endpoint has no response body
+    pub async fn call(&self, method: AdminMethod, args: serde_json::Value) -> Result<serde_json::Value> {
+        let handled = self.handle(method, args);
+
+        match method.feed_update() {
+            FeedUpdate::Sync => {
+                let result = handled.await;
+                self.data_feed.sync().await;
+                result
+            }
+            // The state is replaced while the feed is held, so it can't report the new chain ahead of the reset
+            FeedUpdate::Reset(reason) => self.data_feed.reset_with(reason, handled, Result::is_ok).await,
+            FeedUpdate::None => handled.await,
+        }
+    }
+
+    async fn handle(&self, method: AdminMethod, args: serde_json::Value) -> Result<serde_json::Value> {
+        let state = Extension(self.http_handler.clone());
+
+        match method {
+            AdminMethod::Mint => into_value(http::mint_token::mint(Json(parse_mint_args(args)?), state).await),
+            AdminMethod::SetTime => into_value(http::time::set_time(Json(parse_args(args)?), state).await),
+            AdminMethod::IncreaseTime => into_value(http::time::increase_time(Json(parse_args(args)?), state).await),
+            AdminMethod::CreateBlock => into_value(http::blocks::create_block(state).await),
+            AdminMethod::AbortBlocks => into_value(http::blocks::abort_blocks(Json(parse_args(args)?), state).await),
+            AdminMethod::Restart => into_value(http::restart(state).await),
+            AdminMethod::Dump => into_value(http::dump_load::dump(Json(parse_args(args)?), state).await),
+            AdminMethod::Load => into_value(http::dump_load::load(Json(parse_args(args)?), state).await),
+            AdminMethod::AccountBalance => {
+                into_value(http::accounts::get_account_balance(Query(parse_args(args)?), state).await)
+            }
+            AdminMethod::FeeToken => into_value(http::mint_token::get_fee_token().await),
+            AdminMethod::ForkStatus => into_value(http::get_fork_status(state).await),
+        }
+    }
+}
+
+/// Deserializes the request model an endpoint takes
+fn parse_args<T: DeserializeOwned>(args: serde_json::Value) -> Result<T> {
+    serde_json::from_value(args).map_err(|err| {
+        InvalidArgumentValueSnafu {
+            name: "args",
+            message: err.to_string(),
+        }
+        .build()
+    })
+}
+
+/// Mint amounts exceed what JS numbers hold exactly, so they come as decimal or 0x-prefixed hex strings.
+/// The amount is put back into the request as a JSON number, which the request model takes whole
+fn parse_mint_args<T: DeserializeOwned>(mut args: serde_json::Value) -> Result<T> {
+    let amount = args
+        .as_object_mut()
+        .and_then(|args| args.remove("amount"))
+        .and_then(|amount| amount.as_str().and_then(parse_amount))
+        .context(InvalidArgumentValueSnafu {
+            name: "amount",
+            message: "expected a decimal or 0x-prefixed hex string",
+        })?;
+
+    // serde_json::Value numbers don't go beyond 64 bits, unlike those parsed from text
+    let fields = serde_json::to_string(&args)?;
+    let separator = if fields == "{}" { "" } else { "," };
+    let request = format!("{{\"amount\":{}{}{}", amount, separator, &fields[1..]);
+
+    serde_json::from_str(&request).map_err(|err| {
+        InvalidArgumentValueSnafu {
+            name: "args",
+            message: err.to_string(),
+        }
+        .build()
+    })
+}
+
+fn parse_amount(amount: &str) -> Option<u128> {
+    match amount.strip_prefix("0x") {
+        Some(hex) => u128::from_str_radix(hex, 16).ok(),
+        None => amount.parse().ok(),
+    }
+}
+
+/// Response an endpoint handler returns on success
+trait EndpointResponse {
+    fn into_value(self) -> Result<serde_json::Value>;
+}
+
+impl<T: Serialize> EndpointResponse for Json<T> {
+    fn into_value(self) -> Result<serde_json::Value> {
+        Ok(serde_json::to_value(self.0)?)
+    }
+}
+
+// Endpoints that only answer with a status
+impl EndpointResponse for () {
+    fn into_value(self) -> Result<serde_json::Value> {
+        Ok(serde_json::Value::Null)
+    }
+}
+
+/// Failures keep the HTTP status the endpoint would have responded with
+fn into_value<T, E>(result: std::result::Result<T, E>) -> Result<serde_json::Value>
+where
+    T: EndpointResponse,
+    E: IntoResponse + Display,
+{
+    match result {
+        Ok(response) => response.into_value(),
+        Err(err) => {
+            let message = err.to_string();
+            HttpApiSnafu {
+                status: err.into_response().status().as_u16(),
+                message,
+            }
+            .fail()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::Error;
+    use serde::Deserialize;
+
+    #[derive(Deserialize)]
+    struct MintRequest {
+        address: String,
+        amount: u128,
+    }
+
+    #[test]
+    fn mint_amounts_are_taken_whole() {
+        let amount = u128::MAX - 1;
+        for passed in [amount.to_string(), format!("0x{:x}", amount)] {
+            let request = parse_mint_args::<MintRequest>(serde_json::json!({ "address": "0x1", "amount": passed }))
+                .expect("Failed to parse mint args");
+            assert_eq!(request.amount, amount);
+            assert_eq!(request.address, "0x1");
+        }
+    }
+
+    #[test]
+    fn mint_amounts_must_be_strings() {
+        for amount in [
+            serde_json::json!(1000),
+            serde_json::json!("1e3"),
+            serde_json::json!(null),
+        ] {
+            let request = parse_mint_args::<MintRequest>(serde_json::json!({ "address": "0x1", "amount": amount }));
+            assert!(matches!(
+                request,
+                Err(Error::InvalidArgumentValue { name: "amount", .. })
+            ));
+        }
+    }
+}
@@ -0,0 +1,182 @@
+use neon::context::Context;
+use neon::handle::Handle;
+use neon::object::Object;
+use neon::prelude::{JsBoolean, JsNull, JsNumber, JsObject, JsString, JsUndefined, NeonResult};
+use serde::Serialize;
+use std::ops::RangeInclusive;
+
+use crate::errors::{InvalidConfigSnafu, Result};
+
+/// Largest integer a JS number can represent exactly
+pub const MAX_SAFE_INTEGER: u64 = (1 << 53) - 1;
+
+/// A property as read from JS, before any validation
+pub enum RawValue {
+    Missing,
+    Number(f64),
+    String(String),
+    Bool(bool),
+    Other,
+}
+
+impl RawValue {
+    /// Reads a property, treating both `undefined` and `null` as missing
+    pub fn read<'a, C: Context<'a>>(cx: &mut C, object: Handle<'a, JsObject>, key: &str) -> NeonResult<Self> {
+        let value = object.get(cx, key)?;
+        if value.is_a::<JsUndefined, _>(cx) || value.is_a::<JsNull, _>(cx) {
+            return Ok(RawValue::Missing);
+        }
+
+        if let Ok(number) = value.downcast::<JsNumber, _>(cx) {
+            return Ok(RawValue::Number(number.value(cx)));
+        }
+
+        if let Ok(string) = value.downcast::<JsString, _>(cx) {
+            return Ok(RawValue::String(string.value(cx)));
+        }
+
+        if let Ok(boolean) = value.downcast::<JsBoolean, _>(cx) {
+            return Ok(RawValue::Bool(boolean.value(cx)));
+        }
+
+        Ok(RawValue::Other)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct InvalidField {
+    pub field: String,
+    pub message: String,
+}
+
+/// Collects every invalid field instead of bailing out on the first one
+#[derive(Default)]
+pub struct ConfigValidator {
+    invalid_fields: Vec<InvalidField>,
+}
+
+impl ConfigValidator {
+    pub fn invalid(&mut self, field: &str, message: impl Into<String>) {
+        self.invalid_fields.push(InvalidField {
+            field: field.into(),
+            message: message.into(),
+        });
+    }
+
+    /// Returns `T::default()` on failure, which is never observed since [ConfigValidator::finish] fails then
+    pub fn required_integer<T: TryFrom<u64> + Default>(
+        &mut self,
+        field: &str,
+        value: RawValue,
+        range: RangeInclusive<u64>,
+    ) -> T {
+        if let RawValue::Missing = value {
+            self.invalid(field, "is required");
+            return T::default();
+        }
+
+        self.optional_integer(field, value, range).unwrap_or_default()
+    }
+
+    pub fn optional_integer<T: TryFrom<u64>>(
+        &mut self,
+        field: &str,
+        value: RawValue,
+        range: RangeInclusive<u64>,
+    ) -> Option<T> {
+        let number = match value {
+            RawValue::Missing => return None,
+            RawValue::Number(number) => number,
+            _ => {
+                self.invalid(field, "expected a number");
+                return None;
+            }
+        };
+
+        if number.is_nan() {
+            self.invalid(field, "expected a number, got NaN");
+            return None;
+        }
+
+        if !number.is_finite() || number.fract() != 0.0 {
+            self.invalid(field, format!("expected an integer, got {}", number));
+            return None;
+        }
+
+        if number < *range.start() as f64 || number > *range.end() as f64 {
+            self.invalid(
+                field,
+                format!(
+                    "expected a value between {} and {}, got {}",
+                    range.start(),
+                    range.end(),
+                    number
+                ),
+            );
+            return None;
+        }
+
+        match T::try_from(number as u64) {
+            Ok(value) => Some(value),
+            Err(_) => {
+                self.invalid(field, format!("value {} is out of range", number));
+                None
+            }
+        }
+    }
+
+    pub fn optional_string(&mut self, field: &str, value: RawValue) -> Option<String> {
+        match value {
+            RawValue::Missing => None,
+            RawValue::String(string) if string.is_empty() => {
+                self.invalid(field, "must not be empty");
+                None
+            }
+            RawValue::String(string) => Some(string),
+            _ => {
+                self.invalid(field, "expected a string");
+                None
+            }
+        }
+    }
+
+    pub fn optional_bool(&mut self, field: &str, value: RawValue) -> Option<bool> {
+        match value {
+            RawValue::Missing => None,
+            RawValue::Bool(boolean) => Some(boolean),
+            _ => {
+                self.invalid(field, "expected a boolean");
+                None
+            }
+        }
+    }
+
+    /// Parses an optional string with `parse`, reporting `expected` when it fails
+    pub fn optional_parsed<T>(
+        &mut self,
+        field: &str,
+        value: RawValue,
+        expected: &str,
+        parse: impl FnOnce(&str) -> Option<T>,
+    ) -> Option<T> {
+        let string = self.optional_string(field, value)?;
+        match parse(&string) {
+            Some(value) => Some(value),
+            None => {
+                self.invalid(field, format!("expected {}, got '{}'", expected, string));
+                None
+            }
+        }
+    }
+
+    pub fn finish(self) -> Result<()> {
+        if self.invalid_fields.is_empty() {
+            return Ok(());
+        }
+
+        InvalidConfigSnafu {
+            fields: self.invalid_fields,
+        }
+        .fail()
+    }
+}
@@ -0,0 +1,579 @@
+use serde::Serialize;
+use starknet_devnet_server::{
+    api::{
+        json_rpc::{models::BlockIdInput, JsonRpcHandler},
+        Api,
+    },
+    rpc_core::response::ResponseResult,
+    rpc_handler::RpcHandler,
+};
+use starknet_devnet_types::starknet_api::block::BlockNumber;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::sync::{Arc, MutexGuard, PoisonError};
+use tokio::sync::Mutex;
+
+use crate::{
+    errors::{Error, ReplayUnavailableSnafu, Result},
+    js_callback::JsCallbackHolder,
+    types::AccountData,
+};
+
+#[derive(Clone, Serialize)]
+pub struct SentBlock {
+    pub block_number: u64,
+    pub block_hash: serde_json::Value,
+}
+
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChainResetReason {
+    Restart,
+    Load,
+    Revert,
+}
+
+/// Events delivered to the JS data feed callback, tagged by `type`
+#[derive(Clone, Serialize)]
+#[serde(tag = "type")]
+pub enum FeedEvent {
+    NewBlock {
+        block: serde_json::Value,
+    },
+    BlocksAborted {
+        blocks: Vec<SentBlock>,
+    },
+    ChainReset {
+        reason: ChainResetReason,
+        accounts: Vec<AccountData>,
+    },
+    ServerStopped,
+    /// A failure that was delivered as the error argument of the callback
+    Failed {
+        message: String,
+    },
+    /// Blocks that were shed because JS couldn't keep up, they can still be replayed
+    Lagged {
+        skipped: u64,
+        first_skipped_seq: u64,
+        last_skipped_seq: u64,
+    },
+}
+
+impl FeedEvent {
+    /// Only blocks may be shed under backpressure, losing any other event would leave the UI inconsistent
+    fn is_sheddable(&self) -> bool {
+        matches!(self, FeedEvent::NewBlock { .. })
+    }
+}
+
+/// What to do with blocks while the feed queue is full
+#[derive(Clone, Copy, Default)]
+pub enum FeedOverflow {
+    /// Skip them, only reporting how many were skipped
+    #[default]
+    Drop,
+    /// Skip all but the latest one, which is delivered once there is room again
+    Coalesce,
+}
+
+/// How events are handed over to the JS data feed callback
+#[derive(Clone, Copy, Default)]
+pub enum FeedFormat {
+    /// JS objects, converted on the JS thread
+    #[default]
+    Object,
+    /// JSON strings, serialized on the devnet thread
+    Json,
+    /// UTF-8 JSON in a Buffer, serialized on the devnet thread
+    Buffer,
+}
+
+#[derive(Clone, Copy, Default)]
+pub struct FeedOptions {
+    // Unbounded if None
+    pub queue_limit: Option<usize>,
+    pub overflow: FeedOverflow,
+    pub format: FeedFormat,
+    // Number of most recent events kept for [DataFeed::replay_from]
+    pub replay_capacity: Option<usize>,
+}
+
+/// Replay capacity unless configured otherwise
+const DEFAULT_REPLAY_CAPACITY: usize = 1024;
+
+/// A feed event stamped with its position in the feed of one devnet instance
+#[derive(Clone, Serialize)]
+pub struct SequencedEvent {
+    pub seq: u64,
+    #[serde(flatten)]
+    pub event: FeedEvent,
+}
+
+/// A sequenced event in the format requested by the feed options
+pub enum FeedPayload {
+    // Shared with the journal rather than copied
+    Object(Arc<SequencedEvent>),
+    Json(String),
+    Buffer(Vec<u8>),
+}
+
+impl FeedPayload {
+    fn encode(event: Arc<SequencedEvent>, format: FeedFormat) -> Result<Self> {
+        Ok(match format {
+            FeedFormat::Object => FeedPayload::Object(event),
+            FeedFormat::Json => FeedPayload::Json(serde_json::to_string(event.as_ref())?),
+            FeedFormat::Buffer => FeedPayload::Buffer(serde_json::to_vec(event.as_ref())?),
+        })
+    }
+}
+
+/// Events shed since the feed queue filled up
+#[derive(Default)]
+struct FeedLag {
+    // Skipped sequence numbers are contiguous, since any non-sheddable event ends the lag
+    skipped: Option<(u64, u64)>,
+    // Latest shed event when coalescing
+    latest: Option<Arc<SequencedEvent>>,
+}
+
+struct FeedJournal {
+    // Zero until the first event is sent
+    last_seq: u64,
+    // Events are shared with the payloads in flight, so retaining them costs no copies
+    recent_events: VecDeque<Arc<SequencedEvent>>,
+    replay_capacity: usize,
+    lag: Option<FeedLag>,
+    drain_scheduled: bool,
+}
+
+impl FeedJournal {
+    fn new(replay_capacity: usize) -> Self {
+        Self {
+            last_seq: 0,
+            recent_events: VecDeque::new(),
+            replay_capacity,
+            lag: None,
+            drain_scheduled: false,
+        }
+    }
+
+    fn record(&mut self, event: FeedEvent) -> Arc<SequencedEvent> {
+        self.last_seq += 1;
+
+        let event = Arc::new(SequencedEvent {
+            seq: self.last_seq,
+            event,
+        });
+        if self.replay_capacity == 0 {
+            return event;
+        }
+
+        if self.recent_events.len() == self.replay_capacity {
+            self.recent_events.pop_front();
+        }
+        self.recent_events.push_back(event.clone());
+
+        event
+    }
+
+    fn shed(&mut self, event: Arc<SequencedEvent>, overflow: FeedOverflow) {
+        let lag = self.lag.get_or_insert_with(Default::default);
+        let skipped = match overflow {
+            FeedOverflow::Drop => Some(event),
+            FeedOverflow::Coalesce => lag.latest.replace(event),
+        };
+
+        if let Some(skipped) = skipped {
+            lag.skipped = match lag.skipped {
+                Some((first, _)) => Some((first, skipped.seq)),
+                None => Some((skipped.seq, skipped.seq)),
+            };
+        }
+    }
+}
+
+/// Watches the chain tip of a devnet and pushes blocks to the JS data feed callback.
+/// Shared by every entry point that can change state, so it doesn't matter which route produced a block
+#[derive(Clone)]
+pub struct DataFeed {
+    json_rpc_handler: JsonRpcHandler,
+    js_callback: Arc<JsCallbackHolder<Result<FeedPayload>>>,
+    // Sequencing and handing over to the callback happen under this lock, so events arrive in sequence order
+    journal: Arc<std::sync::Mutex<FeedJournal>>,
+    // Blocks delivered to the data feed that are still considered canonical, in ascending order
+    sent_blocks: Arc<Mutex<Vec<SentBlock>>>,
+    options: FeedOptions,
+}
+
+impl DataFeed {
+    pub fn new(api: Api, js_callback: JsCallbackHolder<Result<FeedPayload>>, options: FeedOptions) -> Self {
+        Self {
+            json_rpc_handler: JsonRpcHandler { api },
+            js_callback: Arc::new(js_callback),
+            journal: Arc::new(std::sync::Mutex::new(FeedJournal::new(
+                options.replay_capacity.unwrap_or(DEFAULT_REPLAY_CAPACITY),
+            ))),
+            sent_blocks: Arc::new(Mutex::new(vec![])),
+            options,
+        }
+    }
+
+    /// Reports blocks that are no longer canonical, then sends every block up to the current chain tip
+    pub async fn sync(&self) {
+        // Held while sending so that concurrent syncs can't interleave blocks
+        let mut sent_blocks = self.sent_blocks.lock().await;
+        self.sync_locked(&mut sent_blocks).await;
+    }
+
+    /// Replaces the chain wholesale by running `replace`. If `replaced` holds for its outcome, the reset is announced
+    /// and the new chain is delivered from genesis. No sync runs in between, so nothing of the new chain is sent
+    /// ahead of the announcement
+    pub async fn reset_with<R>(
+        &self,
+        reason: ChainResetReason,
+        replace: impl Future<Output = R>,
+        replaced: impl FnOnce(&R) -> bool,
+    ) -> R {
+        let mut sent_blocks = self.sent_blocks.lock().await;
+        let outcome = replace.await;
+        if !replaced(&outcome) {
+            return outcome;
+        }
+
+        sent_blocks.clear();
+
+        let accounts = self
+            .json_rpc_handler
+            .api
+            .starknet
+            .read()
+            .await
+            .get_predeployed_accounts()
+            .into_iter()
+            .map(AccountData::from)
+            .collect::<Vec<AccountData>>();
+        self.send_event(FeedEvent::ChainReset { reason, accounts });
+
+        self.sync_locked(&mut sent_blocks).await;
+
+        outcome
+    }
+
+    async fn sync_locked(&self, sent_blocks: &mut Vec<SentBlock>) {
+        let latest_block = self.json_rpc_handler.api.starknet.read().await.get_latest_block();
+        let (latest_block_number, latest_block_hash) = match latest_block {
+            Ok(block) => (block.block_number(), serde_json::to_value(block.block_hash()).ok()),
+            // No blocks yet
+            Err(_) => return,
+        };
+
+        // Most calls don't touch the chain, so skip the walk while the tip is the last sent block
+        let tip_unchanged = sent_blocks.last().is_some_and(|sent_block| {
+            sent_block.block_number == latest_block_number.0
+                && latest_block_hash.as_ref() == Some(&sent_block.block_hash)
+        });
+        if tip_unchanged {
+            return;
+        }
+
+        let aborted_blocks = self.pop_aborted_blocks(sent_blocks, latest_block_number).await;
+        if !aborted_blocks.is_empty() {
+            self.send_event(FeedEvent::BlocksAborted { blocks: aborted_blocks });
+        }
+
+        let first_unsent = sent_blocks.last().map_or(0, |block| block.block_number + 1);
+        for block_number in first_unsent..=latest_block_number.0 {
+            if let Some(sent_block) = self.send_block(BlockNumber(block_number)).await {
+                sent_blocks.push(sent_block);
+            }
+        }
+    }
+
+    /// Walks back from the last sent block until one is found that is still part of the chain
+    async fn pop_aborted_blocks(
+        &self,
+        sent_blocks: &mut Vec<SentBlock>,
+        latest_block_number: BlockNumber,
+    ) -> Vec<SentBlock> {
+        let mut aborted_blocks = vec![];
+        while let Some(sent_block) = sent_blocks.last() {
+            if sent_block.block_number <= latest_block_number.0 {
+                let block_hash = self.get_block_hash(BlockNumber(sent_block.block_number)).await;
+                if block_hash.as_ref() == Some(&sent_block.block_hash) {
+                    break;
+                }
+            }
+
+            aborted_blocks.extend(sent_blocks.pop());
+        }
+
+        aborted_blocks.reverse();
+        aborted_blocks
+    }
+
+    /// Looks the hash up without serializing the transactions of the block
+    async fn get_block_hash(&self, block_number: BlockNumber) -> Option<serde_json::Value> {
+        let block = self
+            .json_rpc_handler
+            .on_request(<JsonRpcHandler as RpcHandler>::Request::BlockWithTransactionHashes(
+                BlockIdInput {
+                    block_id: starknet_core::types::BlockId::Number(block_number.0).into(),
+                },
+            ))
+            .await;
+
+        match block {
+            ResponseResult::Success(block) => block.get("block_hash").cloned(),
+            ResponseResult::Error(_) => None,
+        }
+    }
+
+    async fn get_block(&self, block_number: BlockNumber) -> ResponseResult {
+        self.json_rpc_handler
+            .on_request(<JsonRpcHandler as RpcHandler>::Request::BlockWithFullTransactions(
+                BlockIdInput {
+                    block_id: starknet_core::types::BlockId::Number(block_number.0).into(),
+                },
+            ))
+            .await
+    }
+
+    /// Resolves once every event sent so far has been delivered to JS
+    pub async fn flush(&self) {
+        self.js_callback.flush().await;
+    }
+
+    /// Stops delivering events, without waiting for the queued ones
+    pub fn close(&self) {
+        self.js_callback.close();
+    }
+
+    /// Number of events that are queued but haven't reached JS yet
+    pub fn queue_depth(&self) -> usize {
+        self.js_callback.queue_depth()
+    }
+
+    /// Announces that the server has shut down, no events follow
+    pub fn stopped(&self) {
+        self.send_event(FeedEvent::ServerStopped);
+    }
+
+    /// Sequence number of the last sent event, zero if nothing was sent yet
+    pub fn cursor(&self) -> u64 {
+        self.lock_journal().last_seq
+    }
+
+    /// Returns the retained events with a sequence number of at least `seq`.
+    /// Fails if some of them were already evicted from the journal
+    pub fn replay_from(&self, seq: u64) -> Result<Vec<SequencedEvent>> {
+        let journal = self.lock_journal();
+        let oldest_seq = journal.last_seq + 1 - journal.recent_events.len() as u64;
+        if oldest_seq > 1 && seq < oldest_seq {
+            return ReplayUnavailableSnafu {
+                requested: seq,
+                oldest: oldest_seq,
+            }
+            .fail();
+        }
+
+        Ok(journal
+            .recent_events
+            .iter()
+            .filter(|event| event.seq >= seq)
+            .map(|event| event.as_ref().clone())
+            .collect())
+    }
+
+    fn lock_journal(&self) -> MutexGuard<'_, FeedJournal> {
+        self.journal.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn send_event(&self, event: FeedEvent) {
+        let mut journal = self.lock_journal();
+
+        let queue_full = self
+            .options
+            .queue_limit
+            .is_some_and(|queue_limit| self.js_callback.queue_depth() >= queue_limit);
+        if queue_full && event.is_sheddable() {
+            let event = journal.record(event);
+            journal.shed(event, self.options.overflow);
+            self.schedule_drain(&mut journal);
+            return;
+        }
+
+        // Lag goes first, so that events keep arriving in sequence order
+        self.deliver_lag(&mut journal);
+
+        let event = journal.record(event);
+        self.deliver(event);
+    }
+
+    /// Encodes an event off the JS thread, unless JS objects were requested
+    fn deliver(&self, event: Arc<SequencedEvent>) {
+        let seq = event.seq;
+        let payload = FeedPayload::encode(event, self.options.format).map_err(|source| Error::FeedError {
+            seq,
+            source: Box::new(source),
+        });
+        self.js_callback.call(payload);
+    }
+
+    /// Hands over what is left of a lag: the coalesced event, if any, followed by a [FeedEvent::Lagged]
+    fn deliver_lag(&self, journal: &mut FeedJournal) {
+        let lag = match journal.lag.take() {
+            Some(lag) => lag,
+            None => return,
+        };
+
+        if let Some(latest) = lag.latest {
+            self.deliver(latest);
+        }
+
+        if let Some((first_skipped_seq, last_skipped_seq)) = lag.skipped {
+            let lagged = journal.record(FeedEvent::Lagged {
+                skipped: last_skipped_seq - first_skipped_seq + 1,
+                first_skipped_seq,
+                last_skipped_seq,
+            });
+            self.deliver(lagged);
+        }
+    }
+
+    /// Ends the lag once JS has caught up, even if no further events come in
+    fn schedule_drain(&self, journal: &mut FeedJournal) {
+        if journal.drain_scheduled {
+            return;
+        }
+        journal.drain_scheduled = true;
+
+        let data_feed = self.clone();
+        tokio::spawn(async move {
+            data_feed.flush().await;
+
+            let mut journal = data_feed.lock_journal();
+            journal.drain_scheduled = false;
+            data_feed.deliver_lag(&mut journal);
+        });
+    }
+
+    /// Delivers a failure as the first argument of the callback.
+    /// It is sequenced like an event, so that a UI reloading from the cursor can tell it missed one
+    fn send_error(&self, error: Error) {
+        let mut journal = self.lock_journal();
+        self.deliver_lag(&mut journal);
+
+        let failed = journal.record(FeedEvent::Failed {
+            message: error.to_string(),
+        });
+        self.js_callback.call(Err(Error::FeedError {
+            seq: failed.seq,
+            source: Box::new(error),
+        }));
+    }
+
+    /// Returns the sent block, or None if the block couldn't be retrieved
+    async fn send_block(&self, block_number: BlockNumber) -> Option<SentBlock> {
+        match self.get_block(block_number).await {
+            ResponseResult::Success(block) => {
+                let sent_block = SentBlock {
+                    block_number: block_number.0,
+                    block_hash: block.get("block_hash").cloned().unwrap_or_default(),
+                };
+                self.send_event(FeedEvent::NewBlock { block });
+
+                Some(sent_block)
+            }
+            ResponseResult::Error(err) => {
+                self.send_error(err.into());
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use starknet_devnet_core::starknet::{starknet_config::StarknetConfig, Starknet};
+    use std::thread;
+
+    fn block(sender: u64, index: u64) -> FeedEvent {
+        FeedEvent::NewBlock {
+            block: serde_json::json!({ "sender": sender, "index": index }),
+        }
+    }
+
+    fn data_feed() -> (DataFeed, thread::JoinHandle<Vec<Result<FeedPayload>>>) {
+        let starknet = Starknet::new(&StarknetConfig::default()).expect("Failed to create Starknet");
+        let (js_callback, js_thread) = JsCallbackHolder::recording();
+
+        (
+            DataFeed::new(Api::new(starknet), js_callback, FeedOptions::default()),
+            js_thread,
+        )
+    }
+
+    #[test]
+    fn events_sent_from_many_threads_are_delivered_in_sequence_order() {
+        const THREADS: u64 = 8;
+        const EVENTS_PER_THREAD: u64 = 100;
+        let (data_feed, js_thread) = data_feed();
+
+        let senders = (0..THREADS)
+            .map(|sender| {
+                let data_feed = data_feed.clone();
+                thread::spawn(move || {
+                    for index in 0..EVENTS_PER_THREAD {
+                        data_feed.send_event(block(sender, index));
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for sender in senders {
+            sender.join().expect("Sender panicked");
+        }
+
+        drop(data_feed);
+        let events = js_thread
+            .join()
+            .expect("JS thread panicked")
+            .into_iter()
+            .map(|payload| match payload {
+                Ok(FeedPayload::Object(event)) => event.as_ref().clone(),
+                _ => panic!("Expected an object payload"),
+            })
+            .collect::<Vec<SequencedEvent>>();
+
+        let seqs = events.iter().map(|event| event.seq).collect::<Vec<u64>>();
+        assert_eq!(seqs, (1..=THREADS * EVENTS_PER_THREAD).collect::<Vec<u64>>());
+
+        // Events of each thread keep the order they were sent in
+        for sender in 0..THREADS {
+            let indices = events
+                .iter()
+                .filter_map(|event| match &event.event {
+                    FeedEvent::NewBlock { block } if block["sender"] == sender => block["index"].as_u64(),
+                    _ => None,
+                })
+                .collect::<Vec<u64>>();
+            assert_eq!(indices, (0..EVENTS_PER_THREAD).collect::<Vec<u64>>());
+        }
+    }
+
+    #[test]
+    fn journal_keeps_the_most_recent_events() {
+        let mut journal = FeedJournal::new(DEFAULT_REPLAY_CAPACITY);
+        for index in 0..DEFAULT_REPLAY_CAPACITY as u64 + 10 {
+            journal.record(block(0, index));
+        }
+
+        assert_eq!(journal.recent_events.len(), DEFAULT_REPLAY_CAPACITY);
+        assert_eq!(journal.recent_events.front().map(|event| event.seq), Some(11));
+        assert_eq!(
+            journal.recent_events.back().map(|event| event.seq),
+            Some(journal.last_seq)
+        );
+    }
+}
@@ -1,31 +1,59 @@
 use neon::prelude::*;
-use neon::result::Throw;
-use starknet_devnet_core::starknet::{starknet_config::StarknetConfig, Starknet};
+use neon::types::JsPromise;
+use snafu::OptionExt;
+use starknet_devnet_core::starknet::{
+    starknet_config::{DumpOn, StarknetConfig},
+    Starknet,
+};
 use starknet_devnet_server::{
     api::{http::HttpApiHandler, json_rpc::JsonRpcHandler, Api},
     builder::StarknetDevnetServer,
     ServerConfig,
 };
+use std::future::Future;
 use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tokio::sync::oneshot;
 
 use crate::{
-    errors::Result,
-    js_callback::JsCallbackHolder,
-    js_traits::FromJsValue,
+    admin_api::AdminMethod,
+    data_feed::{DataFeed, FeedOptions, FeedPayload},
+    devnet_instance::DevnetInstance,
+    errors::{Error, InvalidArgumentSnafu, PortsUnavailableSnafu, Result},
+    js_callback::{JsCallbackHolder, JsDeferredHolder},
+    js_traits::{FromJsValue, IntoJsType},
     json_rpc_wrapper::JsonRpcWrapper,
-    server_builder::serve_http_api_json_rpc,
-    types::{AccountData, DevnetConfig},
+    read_api::{ReadArgs, ReadMethod},
+    registry::{guard_env, REGISTRY},
+    server_builder::{serve_http_api_json_rpc, PortAttempt},
+    types::{AccountData, DevnetConfig, DevnetStartResult},
 };
 
 pub struct DevnetAdapter;
 
 impl DevnetAdapter {
     pub fn export(cx: &mut ModuleContext) -> NeonResult<()> {
-        cx.export_function("createDevnetServer", DevnetAdapter::create_devnet_server)
+        cx.export_function("createDevnetServer", DevnetAdapter::create_devnet_server)?;
+        cx.export_function("stopDevnetServer", DevnetAdapter::stop_devnet_server)?;
+        cx.export_function("getFeedQueueDepth", DevnetAdapter::get_feed_queue_depth)?;
+        cx.export_function("getFeedCursor", DevnetAdapter::get_feed_cursor)?;
+        cx.export_function("replayFrom", DevnetAdapter::replay_from)?;
+        cx.export_function("listDevnetInstances", DevnetAdapter::list_devnet_instances)?;
+        cx.export_function("getDevnetInstance", DevnetAdapter::get_devnet_instance)?;
+        cx.export_function("stopAllDevnetServers", DevnetAdapter::stop_all_devnet_servers)?;
+        cx.export_function("request", DevnetAdapter::request)?;
+        cx.export_function("adminRequest", DevnetAdapter::admin_request)?;
+        cx.export_function("readRequest", DevnetAdapter::read_request)?;
+        cx.export_function("takeSnapshot", DevnetAdapter::take_snapshot)?;
+        cx.export_function("revertSnapshot", DevnetAdapter::revert_snapshot)?;
+        cx.export_function("listSnapshots", DevnetAdapter::list_snapshots)?;
+        cx.export_function("deleteSnapshot", DevnetAdapter::delete_snapshot)?;
+
+        guard_env(cx)
     }
 
     fn create_starknet(config: DevnetConfig) -> Result<Starknet> {
-        let starknet_config: StarknetConfig = config.into();
+        let starknet_config = StarknetConfig::try_from(config)?;
         let mut starknet = Starknet::new(&starknet_config)?;
         if let Some(start_time) = starknet_config.start_time {
             starknet.set_block_timestamp_shift(start_time as i64 - Starknet::get_unix_timestamp_as_seconds() as i64);
@@ -34,101 +62,454 @@ impl DevnetAdapter {
         Ok(starknet)
     }
 
-    // Has to be created within tokio rt
+    // Has to be created within tokio rt. No server is created if there are no ports to try
     fn create_server_wrapper(
         starknet: Starknet,
-        datafeed_callback: JsCallbackHolder<serde_json::Value>,
-    ) -> Result<StarknetDevnetServer> {
+        datafeed_callback: JsCallbackHolder<Result<FeedPayload>>,
+        feed_options: FeedOptions,
+        ports: &[u16],
+    ) -> Result<(Option<StarknetDevnetServer>, Api, JsonRpcWrapper, DataFeed)> {
         let config = starknet.config.clone();
 
         let api = Api::new(starknet);
         let json_rpc_handler = JsonRpcHandler { api: api.clone() };
         let http_handler = HttpApiHandler { api: api.clone() };
 
-        let addr: SocketAddr = SocketAddr::new(config.host, config.port);
-        let json_rpc_wrapper = JsonRpcWrapper::new(json_rpc_handler, datafeed_callback);
-        let server = serve_http_api_json_rpc(addr, ServerConfig::default(), json_rpc_wrapper, http_handler, &config)?;
+        let data_feed = DataFeed::new(api.clone(), datafeed_callback, feed_options);
+        let json_rpc_wrapper = JsonRpcWrapper::new(json_rpc_handler, data_feed.clone());
+
+        if ports.is_empty() {
+            return Ok((None, api, json_rpc_wrapper, data_feed));
+        }
+
+        let mut attempts = vec![];
+        for &port in ports {
+            let addr: SocketAddr = SocketAddr::new(config.host, port);
+            let server = serve_http_api_json_rpc(
+                addr,
+                ServerConfig::default(),
+                json_rpc_wrapper.clone(),
+                http_handler.clone(),
+                &config,
+                &data_feed,
+            );
 
-        Ok(server)
+            match server {
+                Ok(server) => return Ok((Some(server), api, json_rpc_wrapper, data_feed)),
+                // Without fallback the error is reported as is
+                Err(err) if ports.len() == 1 => return Err(err),
+                Err(err) => attempts.push(PortAttempt {
+                    port,
+                    message: err.to_string(),
+                }),
+            }
+        }
+
+        PortsUnavailableSnafu { attempts }.fail()
     }
 
-    fn extract_promisified_callback(cx: &mut FunctionContext) -> Result<JsCallbackHolder<Result<Vec<AccountData>>>> {
-        let callback = cx.argument::<JsFunction>(0)?.root(cx);
-        let channel = cx.channel();
-        Ok(JsCallbackHolder::new(callback, channel))
+    /// Dumps the chain if it was configured to be dumped on exit
+    async fn dump_on_exit(api: &Api) -> Result<()> {
+        let starknet = api.starknet.read().await;
+        if starknet.config.dump_on == Some(DumpOn::Exit) {
+            starknet.dump_events()?;
+        }
+
+        Ok(())
     }
 
-    fn extract_datafeed_callback(cx: &mut FunctionContext) -> Result<JsCallbackHolder<serde_json::Value>> {
-        let callback = cx.argument::<JsFunction>(2)?.root(cx);
+    /// Reads an argument without throwing, so that a bad one can reject the promise instead
+    fn argument<'a, V: Value>(cx: &mut FunctionContext<'a>, index: i32, name: &'static str) -> Result<Handle<'a, V>> {
+        cx.argument_opt(index)
+            .and_then(|arg| arg.downcast::<V, _>(cx).ok())
+            .context(InvalidArgumentSnafu { name })
+    }
+
+    fn extract_datafeed_callback(cx: &mut FunctionContext) -> Result<JsCallbackHolder<Result<FeedPayload>>> {
+        let callback = Self::argument::<JsFunction>(cx, 1, "provider")?.root(cx);
         let channel = cx.channel();
-        Ok(JsCallbackHolder::<serde_json::Value>::new(callback, channel))
+        Ok(JsCallbackHolder::<Result<FeedPayload>>::new(callback, channel))
     }
 
+    #[allow(clippy::type_complexity)]
     fn extract_args(
         cx: &mut FunctionContext,
-    ) -> Result<(
-        JsCallbackHolder<Result<Vec<AccountData>>>,
-        JsCallbackHolder<serde_json::Value>,
-        DevnetConfig,
-    )> {
-        let config = cx.argument::<JsObject>(1)?;
+    ) -> NeonResult<Result<(JsCallbackHolder<Result<FeedPayload>>, DevnetConfig, serde_json::Value)>> {
+        let config = match Self::argument::<JsObject>(cx, 0, "config") {
+            Ok(config) => config,
+            Err(err) => return Ok(Err(err)),
+        };
+        // Only throws if reading the config object itself throws
         let config = DevnetConfig::from_js_value(cx, config)?;
 
-        let promisified_callback = Self::extract_promisified_callback(cx)?;
-        let datafeed_callback = Self::extract_datafeed_callback(cx)?;
-
-        Ok((promisified_callback, datafeed_callback, config))
+        Ok(Self::extract_datafeed_callback(cx).and_then(|datafeed_callback| {
+            let config = config?;
+            // Listed with running instances
+            let config_json = config.to_json()?;
+            Ok((datafeed_callback, config, config_json))
+        }))
     }
 
-    fn create_devnet_server(mut cx: FunctionContext) -> JsResult<JsUndefined> {
-        let (mut promisified_callback, datafeed_callback, config) = match Self::extract_args(&mut cx) {
-            Ok(val) => val,
-            Err(_) => return JsResult::Err(Throw {}),
-        };
+    fn create_devnet_server(mut cx: FunctionContext) -> JsResult<JsPromise> {
+        let (deferred, promise) = JsDeferredHolder::<DevnetStartResult>::new(&mut cx);
 
-        let starknet = match Self::create_starknet(config) {
+        let args = Self::extract_args(&mut cx)?.and_then(|(datafeed_callback, config, config_json)| {
+            // Taken before the config is consumed by the starknet one
+            let feed_options = config.feed_options();
+            let ports = config.candidate_ports();
+            Ok((
+                datafeed_callback,
+                Self::create_starknet(config)?,
+                feed_options,
+                ports,
+                config_json,
+            ))
+        });
+        let (datafeed_callback, starknet, feed_options, ports, config_json) = match args {
             Ok(val) => val,
             Err(err) => {
-                promisified_callback.call(Result::<Vec<AccountData>>::Err(err));
-                return Ok(cx.undefined());
+                deferred.settle(Err(err));
+                return Ok(promise);
             }
         };
 
-        std::thread::spawn(move || {
+        let id = REGISTRY.next_id();
+        let env_thread = std::thread::current().id();
+        let (shutdown_sender, shutdown_receiver) = oneshot::channel::<()>();
+        let server_thread = Arc::new(Mutex::new(None));
+
+        let instance_server_thread = server_thread.clone();
+        let handle = std::thread::spawn(move || {
             let rt = match tokio::runtime::Runtime::new() {
                 Ok(rt) => rt,
                 Err(err) => {
-                    promisified_callback.call(Result::<Vec<AccountData>>::Err(err.into()));
-                    return;
+                    deferred.settle(Err(err.into()));
+                    return Ok(());
                 }
             };
 
             rt.block_on(async move {
                 let predeployed_accounts = starknet.get_predeployed_accounts();
+                let dumps_on_exit = starknet.config.dump_on == Some(DumpOn::Exit);
 
                 // Has to be created within tokio env
-                let server = match Self::create_server_wrapper(starknet, datafeed_callback) {
-                    Ok(server) => server,
-                    Err(err) => {
-                        promisified_callback.call(Result::<Vec<AccountData>>::Err(err));
-                        return;
-                    }
-                };
+                let (server, api, json_rpc_wrapper, data_feed) =
+                    match Self::create_server_wrapper(starknet, datafeed_callback, feed_options, &ports) {
+                        Ok(val) => val,
+                        Err(err) => {
+                            deferred.settle(Err(err));
+                            return Ok(());
+                        }
+                    };
+
+                // Server is listening at this point, so the UI can start from the existing chain right away
+                data_feed.sync().await;
 
                 {
-                    let predeployed_accounts = predeployed_accounts
+                    let accounts = predeployed_accounts
                         .into_iter()
                         .map(AccountData::from)
                         .collect::<Vec<AccountData>>();
+                    let instance = DevnetInstance::new(
+                        id,
+                        shutdown_sender,
+                        instance_server_thread,
+                        data_feed.clone(),
+                        json_rpc_wrapper,
+                        api.clone(),
+                        dumps_on_exit,
+                        tokio::runtime::Handle::current(),
+                    );
+                    let start_result = DevnetStartResult {
+                        id,
+                        accounts,
+                        address: server.as_ref().map(|server| server.local_addr()),
+                        instance,
+                    };
+                    REGISTRY.register(start_result.clone(), config_json, env_thread);
+
+                    deferred.settle(Ok(start_result));
+                }
+
+                match server {
+                    Some(server) => {
+                        let server = server.with_graceful_shutdown(async move {
+                            shutdown_receiver.await.ok();
+                        });
 
-                    promisified_callback.call(Ok(predeployed_accounts));
+                        // spawn the server on a new task
+                        tokio::spawn(server).await.ok();
+                    }
+                    // Headless, in-process calls run on this runtime until stopped
+                    None => {
+                        shutdown_receiver.await.ok();
+                    }
                 }
+                let dumped = Self::dump_on_exit(&api).await;
+                data_feed.stopped();
+                // Stop resolves only after JS has received everything, including the stop event
+                data_feed.flush().await;
+                REGISTRY.unregister(id);
 
-                // spawn the server on a new task
-                tokio::spawn(server).await.ok();
-            });
+                // Reported by stop
+                dumped
+            })
         });
 
-        Ok(cx.undefined())
+        // Stop can only be requested from JS once the start promise is resolved, so the handle is in place by then
+        if let Ok(mut server_thread) = server_thread.lock() {
+            *server_thread = Some(handle);
+        }
+
+        Ok(promise)
+    }
+
+    fn stop_devnet_server(mut cx: FunctionContext) -> JsResult<JsPromise> {
+        let (deferred, promise) = JsDeferredHolder::<()>::new(&mut cx);
+
+        let instance = match Self::argument::<JsBox<DevnetInstance>>(&mut cx, 0, "instance") {
+            Ok(instance) => (**instance).clone(),
+            Err(err) => {
+                deferred.settle(Err(err));
+                return Ok(promise);
+            }
+        };
+
+        // Joining the server thread blocks, so keep it off the JS thread
+        std::thread::spawn(move || {
+            deferred.settle(instance.stop());
+        });
+
+        Ok(promise)
+    }
+
+    fn list_devnet_instances(mut cx: FunctionContext) -> JsResult<JsValue> {
+        match REGISTRY.list().into_js_type(&mut cx) {
+            Ok(val) => Ok(val[0]),
+            Err(err) => Self::throw(&mut cx, err),
+        }
+    }
+
+    /// Returns the same shape as a resolved start, or undefined if no running instance has the id
+    fn get_devnet_instance(mut cx: FunctionContext) -> JsResult<JsValue> {
+        let id = cx.argument::<JsNumber>(0)?.value(&mut cx);
+
+        let start_result = match REGISTRY.get(id as u32) {
+            Some(start_result) if id.fract() == 0.0 => start_result,
+            _ => return Ok(cx.undefined().upcast()),
+        };
+
+        match start_result.into_js_type(&mut cx) {
+            Ok(val) => Ok(val[0]),
+            Err(err) => Self::throw(&mut cx, err),
+        }
+    }
+
+    fn stop_all_devnet_servers(mut cx: FunctionContext) -> JsResult<JsPromise> {
+        let (deferred, promise) = JsDeferredHolder::<()>::new(&mut cx);
+        let instances = REGISTRY.instances();
+
+        // Every instance is stopped even if some fail, the first failure is reported
+        std::thread::spawn(move || {
+            let results = instances.iter().map(DevnetInstance::stop).collect::<Vec<Result<()>>>();
+            deferred.settle(results.into_iter().collect());
+        });
+
+        Ok(promise)
+    }
+
+    /// JSON-RPC call that skips HTTP, resolves with the result or rejects with the JSON-RPC error
+    fn request(mut cx: FunctionContext) -> JsResult<JsPromise> {
+        let (deferred, promise) = JsDeferredHolder::<serde_json::Value>::new(&mut cx);
+
+        let args = Self::extract_request_args(&mut cx).and_then(|(instance, method, params)| {
+            let runtime = instance.runtime()?.clone();
+            Ok((instance, method, params, runtime))
+        });
+        let (instance, method, params, runtime) = match args {
+            Ok(val) => val,
+            Err(err) => {
+                deferred.settle(Err(err));
+                return Ok(promise);
+            }
+        };
+
+        runtime.spawn(async move {
+            deferred.settle(instance.json_rpc().request(&method, params).await);
+        });
+
+        Ok(promise)
+    }
+
+    fn extract_request_args(cx: &mut FunctionContext) -> Result<(DevnetInstance, String, Option<serde_json::Value>)> {
+        let instance = (**Self::argument::<JsBox<DevnetInstance>>(cx, 0, "instance")?).clone();
+        let method = Self::argument::<JsString>(cx, 1, "method")?.value(cx);
+        let params = match cx.argument_opt(2) {
+            Some(params) if !params.is_a::<JsUndefined, _>(cx) => Some(neon_serde2::from_value(cx, params)?),
+            _ => None,
+        };
+
+        Ok((instance, method, params))
+    }
+
+    /// Calls a devnet admin endpoint without HTTP, resolves with its JSON response
+    fn admin_request(mut cx: FunctionContext) -> JsResult<JsPromise> {
+        let (deferred, promise) = JsDeferredHolder::<serde_json::Value>::new(&mut cx);
+
+        let args = Self::extract_request_args(&mut cx).and_then(|(instance, method, args)| {
+            let admin_method = AdminMethod::parse(&method).context(InvalidArgumentSnafu { name: "method" })?;
+            let runtime = instance.runtime()?.clone();
+            Ok((instance, admin_method, args, runtime))
+        });
+        let (instance, admin_method, args, runtime) = match args {
+            Ok(val) => val,
+            Err(err) => {
+                deferred.settle(Err(err));
+                return Ok(promise);
+            }
+        };
+
+        runtime.spawn(async move {
+            let args = args.unwrap_or_else(|| serde_json::json!({}));
+            deferred.settle(instance.admin().call(admin_method, args).await);
+        });
+
+        Ok(promise)
+    }
+
+    /// Typed read-only query, resolves with the JSON-RPC result
+    fn read_request(mut cx: FunctionContext) -> JsResult<JsPromise> {
+        let (deferred, promise) = JsDeferredHolder::<serde_json::Value>::new(&mut cx);
+
+        let args = Self::extract_request_args(&mut cx).and_then(|(instance, method, args)| {
+            let read_method = ReadMethod::parse(&method).context(InvalidArgumentSnafu { name: "method" })?;
+            let args = match args {
+                Some(args) => ReadArgs::parse(args)?,
+                None => ReadArgs::default(),
+            };
+            let request = read_method.into_request(args)?;
+            let runtime = instance.runtime()?.clone();
+            Ok((instance, request, runtime))
+        });
+        let (instance, request, runtime) = match args {
+            Ok(val) => val,
+            Err(err) => {
+                deferred.settle(Err(err));
+                return Ok(promise);
+            }
+        };
+
+        runtime.spawn(async move {
+            deferred.settle(instance.json_rpc().read(request).await);
+        });
+
+        Ok(promise)
+    }
+
+    /// Runs the task returned by `task` on the runtime of the instance passed as the first argument,
+    /// settling the returned promise with its result
+    fn run_on_instance<T, F, Fut>(cx: &mut FunctionContext, task: F) -> JsResult<JsPromise>
+    where
+        T: 'static + IntoJsType<JsType = JsValue> + Send,
+        F: FnOnce(&mut FunctionContext, DevnetInstance) -> Result<Fut>,
+        Fut: Future<Output = Result<T>> + Send + 'static,
+    {
+        let (deferred, promise) = JsDeferredHolder::<T>::new(cx);
+
+        let task = Self::argument::<JsBox<DevnetInstance>>(cx, 0, "instance").and_then(|instance| {
+            let instance = (**instance).clone();
+            let runtime = instance.runtime()?.clone();
+            Ok((runtime, task(cx, instance)?))
+        });
+        match task {
+            Ok((runtime, task)) => {
+                runtime.spawn(async move {
+                    deferred.settle(task.await);
+                });
+            }
+            Err(err) => deferred.settle(Err(err)),
+        }
+
+        Ok(promise)
+    }
+
+    fn snapshot_id(cx: &mut FunctionContext) -> Result<u32> {
+        let id = Self::argument::<JsNumber>(cx, 1, "id")?.value(cx);
+        if id.fract() != 0.0 || id < 0.0 || id > u32::MAX as f64 {
+            return InvalidArgumentSnafu { name: "id" }.fail();
+        }
+
+        Ok(id as u32)
+    }
+
+    fn take_snapshot(mut cx: FunctionContext) -> JsResult<JsPromise> {
+        Self::run_on_instance(&mut cx, |_, instance| {
+            Ok(async move { instance.snapshots().take().await })
+        })
+    }
+
+    fn revert_snapshot(mut cx: FunctionContext) -> JsResult<JsPromise> {
+        Self::run_on_instance(&mut cx, |cx, instance| {
+            let id = Self::snapshot_id(cx)?;
+            Ok(async move { instance.snapshots().revert(id).await })
+        })
+    }
+
+    fn list_snapshots(mut cx: FunctionContext) -> JsResult<JsPromise> {
+        Self::run_on_instance(&mut cx, |_, instance| {
+            Ok(async move { Ok(instance.snapshots().list().await) })
+        })
+    }
+
+    fn delete_snapshot(mut cx: FunctionContext) -> JsResult<JsPromise> {
+        Self::run_on_instance(&mut cx, |cx, instance| {
+            let id = Self::snapshot_id(cx)?;
+            Ok(async move { instance.snapshots().delete(id).await })
+        })
+    }
+
+    fn get_feed_queue_depth(mut cx: FunctionContext) -> JsResult<JsNumber> {
+        let instance = cx.argument::<JsBox<DevnetInstance>>(0)?;
+        let queue_depth = instance.data_feed().queue_depth();
+
+        Ok(cx.number(queue_depth as f64))
+    }
+
+    fn get_feed_cursor(mut cx: FunctionContext) -> JsResult<JsNumber> {
+        let instance = cx.argument::<JsBox<DevnetInstance>>(0)?;
+        let cursor = instance.data_feed().cursor();
+
+        Ok(cx.number(cursor as f64))
+    }
+
+    fn replay_from(mut cx: FunctionContext) -> JsResult<JsValue> {
+        let args = Self::argument::<JsBox<DevnetInstance>>(&mut cx, 0, "instance").and_then(|instance| {
+            let seq = Self::argument::<JsNumber>(&mut cx, 1, "seq")?.value(&mut cx);
+            if seq.is_nan() || seq < 0.0 {
+                return InvalidArgumentSnafu { name: "seq" }.fail();
+            }
+
+            Ok((instance, seq as u64))
+        });
+        let (instance, seq) = match args {
+            Ok(val) => val,
+            Err(err) => return Self::throw(&mut cx, err),
+        };
+
+        let events = match instance.data_feed().replay_from(seq) {
+            Ok(events) => events,
+            Err(err) => return Self::throw(&mut cx, err),
+        };
+
+        match events.into_js_type(&mut cx) {
+            Ok(val) => Ok(val[0]),
+            Err(err) => Self::throw(&mut cx, err),
+        }
+    }
+
+    /// Throws a crate [Error] as a JS exception
+    fn throw<T: Value>(cx: &mut FunctionContext, err: Error) -> JsResult<T> {
+        let js_error = err.into_js_type(cx).expect("Nani? Error creation shall not panic")[0];
+        cx.throw(js_error)
     }
 }
@@ -0,0 +1,143 @@
+use neon::types::Finalize;
+use starknet_devnet_server::api::{http::HttpApiHandler, Api};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use tokio::runtime::Handle;
+use tokio::sync::oneshot;
+
+use crate::admin_api::AdminApi;
+use crate::data_feed::DataFeed;
+use crate::errors::{InstanceStoppedSnafu, Result, ServerThreadPanicSnafu};
+use crate::json_rpc_wrapper::JsonRpcWrapper;
+use crate::registry::{InstanceStatus, REGISTRY};
+use crate::snapshots::Snapshots;
+
+/// Handle of a running devnet that is handed over to JS as a boxed value
+#[derive(Clone)]
+pub struct DevnetInstance {
+    id: u32,
+    shutdown_sender: Arc<Mutex<Option<oneshot::Sender<()>>>>,
+    server_thread: Arc<Mutex<Option<JoinHandle<Result<()>>>>>,
+    data_feed: DataFeed,
+    json_rpc: JsonRpcWrapper,
+    admin: AdminApi,
+    snapshots: Snapshots,
+    // Shutting down dumps the chain, see [Self::wait_for_exit]
+    dumps_on_exit: bool,
+    // Runtime of the server thread, for running in-process calls
+    runtime: Handle,
+}
+
+impl Finalize for DevnetInstance {}
+
+impl DevnetInstance {
+    pub fn new(
+        id: u32,
+        shutdown_sender: oneshot::Sender<()>,
+        server_thread: Arc<Mutex<Option<JoinHandle<Result<()>>>>>,
+        data_feed: DataFeed,
+        json_rpc: JsonRpcWrapper,
+        api: Api,
+        dumps_on_exit: bool,
+        runtime: Handle,
+    ) -> Self {
+        Self {
+            id,
+            shutdown_sender: Arc::new(Mutex::new(Some(shutdown_sender))),
+            server_thread,
+            admin: AdminApi::new(HttpApiHandler { api: api.clone() }, data_feed.clone()),
+            snapshots: Snapshots::new(api, data_feed.clone()),
+            data_feed,
+            json_rpc,
+            dumps_on_exit,
+            runtime,
+        }
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    pub fn data_feed(&self) -> &DataFeed {
+        &self.data_feed
+    }
+
+    pub fn json_rpc(&self) -> &JsonRpcWrapper {
+        &self.json_rpc
+    }
+
+    pub fn admin(&self) -> &AdminApi {
+        &self.admin
+    }
+
+    pub fn snapshots(&self) -> &Snapshots {
+        &self.snapshots
+    }
+
+    pub fn dumps_on_exit(&self) -> bool {
+        self.dumps_on_exit
+    }
+
+    /// Runtime to run in-process calls on, as long as the server is running
+    pub fn runtime(&self) -> Result<&Handle> {
+        let running = self
+            .shutdown_sender
+            .lock()
+            .is_ok_and(|sender| sender.as_ref().is_some_and(|sender| !sender.is_closed()));
+        if !running {
+            return InstanceStoppedSnafu { id: self.id }.fail();
+        }
+
+        Ok(&self.runtime)
+    }
+
+    /// Signals the server to shut down gracefully and waits until the runtime thread exits.
+    /// Blocks the caller, so it must not be called on the JS main thread
+    pub fn stop(&self) -> Result<()> {
+        self.signal_shutdown();
+
+        let server_thread = self.server_thread.lock().ok().and_then(|mut thread| thread.take());
+        match server_thread {
+            Some(server_thread) => server_thread.join().map_err(|_| ServerThreadPanicSnafu.build())?,
+            // Already stopped
+            None => Ok(()),
+        }
+    }
+
+    /// Signals the server to shut down without waiting for it, dropping the feed events JS hasn't received.
+    /// For when the JS environment is torn down and won't run them anymore
+    pub fn shutdown(&self) {
+        self.data_feed.close();
+        self.signal_shutdown();
+    }
+
+    /// Waits up to `timeout` for the server thread to exit after [Self::shutdown], e.g. to let it dump the chain.
+    /// The thread is left to finish on its own if it takes longer
+    pub fn wait_for_exit(&self, timeout: Duration) {
+        let server_thread = match self.server_thread.lock().ok().and_then(|mut thread| thread.take()) {
+            Some(server_thread) => server_thread,
+            // Already stopped
+            None => return,
+        };
+
+        let deadline = Instant::now() + timeout;
+        while !server_thread.is_finished() && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+
+        if server_thread.is_finished() {
+            // Failures can't be reported anymore, the environment is going away
+            server_thread.join().ok();
+        }
+    }
+
+    fn signal_shutdown(&self) {
+        REGISTRY.set_status(self.id, InstanceStatus::Stopping);
+
+        if let Some(shutdown_sender) = self.shutdown_sender.lock().ok().and_then(|mut sender| sender.take()) {
+            // Receiver is gone if the server has already finished
+            shutdown_sender.send(()).ok();
+        }
+    }
+}
@@ -1,14 +1,20 @@
+use crate::config_validation::InvalidField;
 use crate::js_traits::IntoJsType;
+use crate::server_builder::PortAttempt;
 use neon::context::Context;
 use neon::handle::Handle;
 use neon::object::Object;
 use neon::types::{JsError, JsValue};
 use snafu::{Backtrace, ErrorCompat, Snafu};
+use starknet_devnet_server::rpc_core::error::RpcError;
 
 #[derive(Clone, Copy)]
 enum ErrorType {
     Internal = 0,
     Devnet,
+    Config,
+    // Bad arguments passed to an addon function, other than the devnet config
+    Argument,
 }
 
 impl From<ErrorType> for u32 {
@@ -16,6 +22,8 @@ impl From<ErrorType> for u32 {
         match error_type {
             ErrorType::Internal => 0,
             ErrorType::Devnet => 1,
+            ErrorType::Config => 2,
+            ErrorType::Argument => 3,
         }
     }
 }
@@ -29,6 +37,12 @@ pub enum Error {
         backtrace: Backtrace,
     },
 
+    #[snafu(context(false))]
+    DevnetTypesError {
+        source: starknet_devnet_types::error::Error,
+        backtrace: Backtrace,
+    },
+
     #[snafu(context(false))]
     DevnetServerError {
         source: starknet_devnet_server::error::Error,
@@ -52,6 +66,100 @@ pub enum Error {
         source: neon_serde2::errors::Error,
         backtrace: Backtrace,
     },
+
+    #[snafu(context(false))]
+    JsonError {
+        source: serde_json::Error,
+        backtrace: Backtrace,
+    },
+
+    #[snafu(display("Invalid devnet config: {}", describe_invalid_fields(fields)))]
+    InvalidConfig {
+        fields: Vec<InvalidField>,
+        backtrace: Backtrace,
+    },
+
+    #[snafu(display("Invalid argument {}", name))]
+    InvalidArgument { name: &'static str, backtrace: Backtrace },
+
+    #[snafu(display("Invalid argument {}: {}", name, message))]
+    InvalidArgumentValue {
+        name: &'static str,
+        message: String,
+        backtrace: Backtrace,
+    },
+
+    #[snafu(display("JSON-RPC error {}: {}", code, message))]
+    JsonRpcError {
+        code: i64,
+        message: String,
+        data: Option<serde_json::Value>,
+        backtrace: Backtrace,
+    },
+
+    #[snafu(display(
+        "Feed events from {} can't be replayed, the oldest retained one is {}",
+        requested,
+        oldest
+    ))]
+    ReplayUnavailable {
+        requested: u64,
+        oldest: u64,
+        backtrace: Backtrace,
+    },
+
+    // Sequenced like the feed events around it
+    #[snafu(display("{}", source))]
+    FeedError { seq: u64, source: Box<Error> },
+
+    #[snafu(display("None of the ports could be bound: {}", describe_port_attempts(attempts)))]
+    PortsUnavailable {
+        attempts: Vec<PortAttempt>,
+        backtrace: Backtrace,
+    },
+
+    #[snafu(display("Devnet HTTP API error {}: {}", status, message))]
+    HttpApiError {
+        status: u16,
+        message: String,
+        backtrace: Backtrace,
+    },
+
+    #[snafu(display("Snapshot {} doesn't exist", id))]
+    SnapshotNotFound { id: u32, backtrace: Backtrace },
+
+    #[snafu(display("Devnet instance {} is stopped", id))]
+    InstanceStopped { id: u32, backtrace: Backtrace },
+
+    #[snafu(display("Devnet server thread panicked"))]
+    ServerThreadPanic { backtrace: Backtrace },
+}
+
+fn describe_invalid_fields(fields: &[InvalidField]) -> String {
+    fields
+        .iter()
+        .map(|invalid| format!("{} {}", invalid.field, invalid.message))
+        .collect::<Vec<String>>()
+        .join("; ")
+}
+
+fn describe_port_attempts(attempts: &[PortAttempt]) -> String {
+    attempts
+        .iter()
+        .map(|attempt| format!("{}: {}", attempt.port, attempt.message))
+        .collect::<Vec<String>>()
+        .join("; ")
+}
+
+impl From<RpcError> for Error {
+    fn from(value: RpcError) -> Error {
+        JsonRpcSnafu {
+            code: value.code.code(),
+            message: value.message.to_string(),
+            data: value.data,
+        }
+        .build()
+    }
 }
 
 impl From<neon::result::Throw> for Error {
@@ -64,11 +172,13 @@ struct Info {
     error_type: u32,
     details: String,
     backtrace: String,
+    // Additional properties set on the JS error object
+    properties: Vec<(&'static str, serde_json::Value)>,
 }
 
 impl From<&Error> for Info {
     fn from(value: &Error) -> Self {
-        let backtrace = if let Some(backtrace) =  ErrorCompat::backtrace(value) {
+        let backtrace = if let Some(backtrace) = ErrorCompat::backtrace(value) {
             format!("{:?}", backtrace)
         } else {
             "<empty>".into()
@@ -79,26 +189,125 @@ impl From<&Error> for Info {
                 error_type: ErrorType::Devnet.into(),
                 details: source.to_string(),
                 backtrace,
+                properties: vec![],
+            },
+            Error::DevnetTypesError { source, backtrace: _ } => Info {
+                error_type: ErrorType::Devnet.into(),
+                details: source.to_string(),
+                backtrace,
+                properties: vec![],
             },
             Error::IoError { source, backtrace: _ } => Info {
                 error_type: ErrorType::Internal.into(),
                 details: source.to_string(),
                 backtrace,
+                properties: vec![],
             },
             Error::JsError { error, backtrace: _ } => Info {
                 error_type: ErrorType::Internal.into(),
                 details: error.to_string(),
                 backtrace,
+                properties: vec![],
             },
             Error::NeonSerdeError { source, backtrace: _ } => Info {
                 error_type: ErrorType::Internal.into(),
                 details: source.to_string(),
                 backtrace,
+                properties: vec![],
+            },
+            Error::JsonError { source, backtrace: _ } => Info {
+                error_type: ErrorType::Internal.into(),
+                details: source.to_string(),
+                backtrace,
+                properties: vec![],
             },
             Error::DevnetServerError { source, backtrace: _ } => Info {
                 error_type: ErrorType::Devnet.into(),
                 details: source.to_string(),
                 backtrace,
+                properties: vec![],
+            },
+            Error::InvalidConfig { fields, backtrace: _ } => Info {
+                error_type: ErrorType::Config.into(),
+                details: value.to_string(),
+                backtrace,
+                properties: vec![("fields", serde_json::to_value(fields).unwrap_or_default())],
+            },
+            Error::InvalidArgument { name: _, backtrace: _ } => Info {
+                error_type: ErrorType::Argument.into(),
+                details: value.to_string(),
+                backtrace,
+                properties: vec![],
+            },
+            Error::InvalidArgumentValue {
+                name: _,
+                message: _,
+                backtrace: _,
+            } => Info {
+                error_type: ErrorType::Argument.into(),
+                details: value.to_string(),
+                backtrace,
+                properties: vec![],
+            },
+            Error::JsonRpcError {
+                code,
+                message: _,
+                data,
+                backtrace: _,
+            } => Info {
+                error_type: ErrorType::Devnet.into(),
+                details: value.to_string(),
+                backtrace,
+                properties: vec![("code", (*code).into()), ("data", data.clone().unwrap_or_default())],
+            },
+            Error::ReplayUnavailable {
+                requested: _,
+                oldest,
+                backtrace: _,
+            } => Info {
+                error_type: ErrorType::Internal.into(),
+                details: value.to_string(),
+                backtrace,
+                properties: vec![("oldest", (*oldest).into())],
+            },
+            Error::FeedError { seq, source } => {
+                let mut info = Info::from(source.as_ref());
+                info.properties.push(("seq", (*seq).into()));
+                info
+            }
+            Error::PortsUnavailable { attempts, backtrace: _ } => Info {
+                error_type: ErrorType::Devnet.into(),
+                details: value.to_string(),
+                backtrace,
+                properties: vec![("attempts", serde_json::to_value(attempts).unwrap_or_default())],
+            },
+            Error::HttpApiError {
+                status,
+                message: _,
+                backtrace: _,
+            } => Info {
+                error_type: ErrorType::Devnet.into(),
+                details: value.to_string(),
+                backtrace,
+                properties: vec![("status", (*status).into())],
+            },
+            Error::SnapshotNotFound { id: _, backtrace: _ } => Info {
+                error_type: ErrorType::Devnet.into(),
+                details: value.to_string(),
+                backtrace,
+                properties: vec![],
+            },
+            Error::InstanceStopped { id: _, backtrace: _ } => Info {
+                error_type: ErrorType::Devnet.into(),
+                details: value.to_string(),
+                backtrace,
+                properties: vec![],
+            },
+            Error::ServerThreadPanic { backtrace: _ } => Info {
+                error_type: ErrorType::Internal.into(),
+                details: value.to_string(),
+                backtrace,
+                properties: vec![],
             },
         }
     }
@@ -126,6 +335,16 @@ impl IntoJsType for Error {
             return Ok(vec![cx.string("Failed to set backtrace").upcast()]);
         }
 
+        for (key, value) in info.properties {
+            let value = match neon_serde2::to_value(cx, &value) {
+                Ok(val) => val,
+                Err(_) => return Ok(vec![cx.string("Failed to serialize error property").upcast()]),
+            };
+            if error.set(cx, key, value).is_err() {
+                return Ok(vec![cx.string("Failed to set error property").upcast()]);
+            }
+        }
+
         let engine = cx.string("alpaca-addon");
         if let Err(_) = error.set(cx, "engine", engine) {
             return Ok(vec![cx.string("Failed to set engine type").upcast()]);
@@ -1,102 +1,371 @@
 use neon::prelude::*;
+use neon::types::{Deferred, JsPromise};
 use std::marker::PhantomData;
 use std::{
     clone::Clone,
     sync::{
-        mpsc::{channel, Receiver, TryRecvError},
-        Arc, Mutex,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
     },
 };
+use tokio::sync::Notify;
 
+use crate::errors::Result;
 use crate::js_traits::IntoJsType;
 
-pub struct JsCallbackHolder<T> {
-    js_callback: Arc<Mutex<Root<JsFunction>>>,
-    event_channel: Arc<Mutex<Channel>>,
-    receivers: Vec<Receiver<()>>,
-    _p: PhantomData<T>,
+/// Number of calls handed over to the JS event loop that haven't run yet
+#[derive(Default)]
+struct PendingCalls {
+    count: AtomicUsize,
+    drained: Notify,
+    // Set once JS is no longer expected to run the calls
+    closed: AtomicBool,
+}
+
+impl PendingCalls {
+    fn start(self: &Arc<Self>) -> PendingCallGuard {
+        self.count.fetch_add(1, Ordering::SeqCst);
+        PendingCallGuard(self.clone())
+    }
+
+    fn depth(&self) -> usize {
+        self.count.load(Ordering::SeqCst)
+    }
+
+    fn close(&self) {
+        self.closed.store(true, Ordering::SeqCst);
+        self.drained.notify_waiters();
+    }
+
+    async fn drained(&self) {
+        loop {
+            // Registered before checking the count so that a notification in between isn't missed
+            let drained = self.drained.notified();
+            if self.depth() == 0 || self.closed.load(Ordering::SeqCst) {
+                return;
+            }
+
+            drained.await;
+        }
+    }
 }
 
-impl<T> Drop for JsCallbackHolder<T> {
+/// Marks a call as finished once dropped, whether it ran or was discarded by the event loop
+struct PendingCallGuard(Arc<PendingCalls>);
+
+impl Drop for PendingCallGuard {
     fn drop(&mut self) {
-        for receiver in self.receivers.iter() {
-            receiver.recv().ok();
+        if self.0.count.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.0.drained.notify_waiters();
         }
     }
 }
 
+/// Hands calls over to the JS thread, which runs them in the order they were queued
+trait CallQueue<T>: Send + Sync {
+    /// Returns false if the call was dropped because the event loop is already gone.
+    /// `pending_call` must be dropped once the call has run or was discarded
+    fn queue(&self, args: T, pending_call: PendingCallGuard) -> bool;
+}
+
+/// Queues calls of a JS function straight onto the event loop [Channel]
+struct ChannelQueue {
+    js_callback: Arc<Root<JsFunction>>,
+    event_channel: Channel,
+}
+
+impl<T> CallQueue<T> for ChannelQueue
+where
+    T: 'static + IntoJsType + Send,
+{
+    fn queue(&self, args: T, pending_call: PendingCallGuard) -> bool {
+        let js_callback = self.js_callback.clone();
+
+        // The guard is dropped with the closure if the event loop is already gone
+        let sent = self.event_channel.try_send(move |mut cx| {
+            let _pending_call = pending_call;
+
+            let callback = js_callback.to_inner(&mut cx);
+            let this = cx.undefined();
+
+            match args.into_js_type(&mut cx) {
+                Ok(val) => {
+                    callback.call(&mut cx, this, val)?;
+                }
+                Err(err) => {
+                    let js_error = err.into_js_type(&mut cx).expect("Nani? Error creation shall not panic");
+                    callback.call(&mut cx, this, js_error)?;
+                }
+            };
+
+            Ok(())
+        });
+
+        sent.is_ok()
+    }
+}
+
+/// Calls a JS function from any thread.
+/// Calls are queued straight onto the event loop [Channel], which runs them in FIFO order.
+/// The queue itself is unbounded, the data feed keeps it short with its queue limit.
+/// Dropping the holder never blocks, queued calls keep the callback alive until they run
+pub struct JsCallbackHolder<T> {
+    queue: Arc<dyn CallQueue<T>>,
+    pending: Arc<PendingCalls>,
+}
+
 impl<T> Clone for JsCallbackHolder<T> {
     fn clone(&self) -> JsCallbackHolder<T> {
         JsCallbackHolder {
-            js_callback: self.js_callback.clone(),
-            event_channel: self.event_channel.clone(),
-            receivers: vec![],
-            _p: Default::default(),
+            queue: self.queue.clone(),
+            pending: self.pending.clone(),
         }
     }
 }
 
-impl<'b, T> JsCallbackHolder<T>
+impl<T> JsCallbackHolder<T>
 where
     T: 'static + IntoJsType + Send,
 {
     pub fn new(js_callback: Root<JsFunction>, event_channel: Channel) -> Self {
+        Self::with_queue(ChannelQueue {
+            js_callback: Arc::new(js_callback),
+            event_channel,
+        })
+    }
+}
+
+impl<T> JsCallbackHolder<T> {
+    fn with_queue(queue: impl CallQueue<T> + 'static) -> Self {
         Self {
-            js_callback: Arc::new(Mutex::new(js_callback)),
-            event_channel: Arc::new(Mutex::new(event_channel)),
-            receivers: vec![],
-            _p: Default::default(),
+            queue: Arc::new(queue),
+            pending: Default::default(),
         }
     }
 
-    pub fn call(&mut self, args: T) {
-        self.prune_finished_tasks();
+    /// Resolves once every call made so far has run on the JS thread or was discarded.
+    /// Must not be awaited on the JS thread itself
+    pub async fn flush(&self) {
+        self.pending.drained().await;
+    }
 
-        let (sender, receiver) = channel::<()>();
+    /// Number of calls that are queued but haven't run on the JS thread yet
+    pub fn queue_depth(&self) -> usize {
+        self.pending.depth()
+    }
 
-        let event_channel = self.event_channel.clone();
-        let js_callback = self.js_callback.clone();
+    /// Drops all further calls and releases [Self::flush], for when the JS event loop is going away
+    pub fn close(&self) {
+        self.pending.close();
+    }
 
-        std::thread::spawn(move || {
-            if let Ok(event_channel) = event_channel.lock() {
-                event_channel.send(move |mut cx| {
-                    if let Ok(locked_callback) = js_callback.lock() {
-                        let callback = locked_callback.to_inner(&mut cx);
-                        let this = cx.undefined();
-
-                        match args.into_js_type(&mut cx) {
-                            Ok(val) => {
-                                callback.call(&mut cx, this, val)?;
-                            }
-                            Err(err) => {
-                                let js_error = err.into_js_type(&mut cx).expect("Nani? Error creation shall not panic");
-                                callback.call(&mut cx, this, js_error)?;
-                            }
-                        };
-                    } else {
-                        // TODO: log poisoned callback mutex
-                    }
-
-                    drop(js_callback);
-                    sender.send(()).ok();
-
-                    Ok(())
-                });
-            } else {
-                // TODO: log poisoned mutex
-            }
+    pub fn call(&self, args: T) {
+        if self.pending.closed.load(Ordering::SeqCst) {
+            return;
+        }
+
+        if !self.queue.queue(args, self.pending.start()) {
+            // TODO: log closed event channel
+        }
+    }
+}
+
+/// Stands in for the event loop in tests, running the queued calls in order on a thread of its own
+#[cfg(test)]
+struct RecordingQueue<T> {
+    sender: std::sync::Mutex<std::sync::mpsc::Sender<(T, PendingCallGuard)>>,
+}
+
+#[cfg(test)]
+impl<T: Send> CallQueue<T> for RecordingQueue<T> {
+    fn queue(&self, args: T, pending_call: PendingCallGuard) -> bool {
+        let sender = self.sender.lock().expect("Queue poisoned");
+        sender.send((args, pending_call)).is_ok()
+    }
+}
+
+#[cfg(test)]
+impl<T: 'static + Send> JsCallbackHolder<T> {
+    /// Holder whose calls are recorded instead of run on the JS thread.
+    /// The returned thread yields them in the order they ran once every clone of the holder is dropped
+    pub fn recording() -> (Self, std::thread::JoinHandle<Vec<T>>) {
+        let (sender, receiver) = std::sync::mpsc::channel::<(T, PendingCallGuard)>();
+        let js_thread = std::thread::spawn(move || receiver.into_iter().map(|(args, _pending_call)| args).collect());
+
+        let queue = RecordingQueue {
+            sender: std::sync::Mutex::new(sender),
+        };
+        (Self::with_queue(queue), js_thread)
+    }
+}
+
+/// Settles a JS promise from any thread.
+/// Resolves with the value, or rejects with the crate [Error](crate::errors::Error) converted to a JS error
+pub struct JsDeferredHolder<T> {
+    deferred: Deferred,
+    event_channel: Channel,
+    _p: PhantomData<T>,
+}
+
+impl<T> JsDeferredHolder<T>
+where
+    T: 'static + IntoJsType<JsType = JsValue> + Send,
+{
+    /// Creates the promise handed back to JS along with its holder
+    pub fn new<'a, C: Context<'a>>(cx: &mut C) -> (Self, Handle<'a, JsPromise>) {
+        let (deferred, promise) = cx.promise();
+        let holder = Self {
+            deferred,
+            event_channel: cx.channel(),
+            _p: Default::default(),
+        };
+
+        (holder, promise)
+    }
+
+    pub fn settle(self, result: Result<T>) {
+        let deferred = self.deferred;
+
+        // The promise is left pending if the event loop is already gone
+        let sent = self.event_channel.try_send(move |mut cx| {
+            match result.and_then(|value| value.into_js_type(&mut cx)) {
+                Ok(val) => {
+                    let value = match val.first() {
+                        Some(value) => *value,
+                        None => cx.undefined().upcast(),
+                    };
+                    deferred.resolve(&mut cx, value);
+                }
+                Err(err) => {
+                    let js_error = err.into_js_type(&mut cx).expect("Nani? Error creation shall not panic")[0];
+                    deferred.reject(&mut cx, js_error);
+                }
+            };
+
+            Ok(())
         });
 
-        self.receivers.push(receiver);
+        if sent.is_err() {
+            // TODO: log closed event channel
+        }
     }
+}
 
-    fn prune_finished_tasks(&mut self) {
-        self.receivers.retain(|receiver| match receiver.try_recv() {
-            Ok(_) => false,
-            Err(error) => match error {
-                TryRecvError::Empty => true,
-                TryRecvError::Disconnected => false,
-            },
-        })
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    const THREADS: usize = 8;
+    const CALLS_PER_THREAD: usize = 100;
+
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .expect("Failed to build runtime");
+        runtime.block_on(future)
+    }
+
+    #[test]
+    fn calls_started_from_many_threads_are_counted() {
+        let pending = Arc::new(PendingCalls::default());
+
+        let senders = (0..THREADS)
+            .map(|_| {
+                let pending = pending.clone();
+                thread::spawn(move || (0..CALLS_PER_THREAD).map(|_| pending.start()).collect::<Vec<_>>())
+            })
+            .collect::<Vec<_>>();
+        let guards = senders
+            .into_iter()
+            .flat_map(|sender| sender.join().expect("Sender panicked"))
+            .collect::<Vec<PendingCallGuard>>();
+        assert_eq!(pending.depth(), THREADS * CALLS_PER_THREAD);
+
+        drop(guards);
+        assert_eq!(pending.depth(), 0);
+    }
+
+    #[test]
+    fn drained_waits_for_calls_finished_on_other_threads() {
+        let pending = Arc::new(PendingCalls::default());
+        let guards = (0..THREADS * CALLS_PER_THREAD)
+            .map(|_| pending.start())
+            .collect::<Vec<_>>();
+
+        // Like the JS thread running calls while the devnet thread waits for them
+        let mut guards = guards.into_iter();
+        let runners = (0..THREADS)
+            .map(|_| {
+                let guards = guards.by_ref().take(CALLS_PER_THREAD).collect::<Vec<_>>();
+                thread::spawn(move || guards.into_iter().for_each(drop))
+            })
+            .collect::<Vec<_>>();
+
+        block_on(pending.drained());
+        assert_eq!(pending.depth(), 0);
+
+        for runner in runners {
+            runner.join().expect("Runner panicked");
+        }
+    }
+
+    #[test]
+    fn close_releases_drained_with_calls_pending() {
+        let pending = Arc::new(PendingCalls::default());
+        let _guard = pending.start();
+
+        let closer = {
+            let pending = pending.clone();
+            thread::spawn(move || pending.close())
+        };
+
+        block_on(pending.drained());
+        assert_eq!(pending.depth(), 1);
+        closer.join().expect("Closer panicked");
+    }
+
+    #[test]
+    fn calls_from_many_threads_run_in_the_order_they_were_made() {
+        let (holder, js_thread) = JsCallbackHolder::<(usize, usize)>::recording();
+
+        let senders = (0..THREADS)
+            .map(|sender| {
+                let holder = holder.clone();
+                thread::spawn(move || (0..CALLS_PER_THREAD).for_each(|index| holder.call((sender, index))))
+            })
+            .collect::<Vec<_>>();
+        for sender in senders {
+            sender.join().expect("Sender panicked");
+        }
+
+        block_on(holder.flush());
+        assert_eq!(holder.queue_depth(), 0);
+
+        drop(holder);
+        let calls = js_thread.join().expect("JS thread panicked");
+        assert_eq!(calls.len(), THREADS * CALLS_PER_THREAD);
+
+        // Calls of each thread run in the order they were made
+        for sender in 0..THREADS {
+            let indices = calls
+                .iter()
+                .filter(|(call_sender, _)| *call_sender == sender)
+                .map(|(_, index)| *index)
+                .collect::<Vec<usize>>();
+            assert_eq!(indices, (0..CALLS_PER_THREAD).collect::<Vec<usize>>());
+        }
+    }
+
+    #[test]
+    fn calls_after_close_are_dropped() {
+        let (holder, js_thread) = JsCallbackHolder::<usize>::recording();
+
+        holder.call(1);
+        holder.close();
+        holder.call(2);
+
+        drop(holder);
+        assert_eq!(js_thread.join().expect("JS thread panicked"), vec![1]);
     }
 }
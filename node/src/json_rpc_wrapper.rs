@@ -1,51 +1,62 @@
 use starknet_devnet_server::{
-    api::json_rpc::{models::BlockIdInput, JsonRpcHandler},
-    rpc_core::response::ResponseResult,
+    api::json_rpc::JsonRpcHandler,
+    rpc_core::{error::RpcError, response::ResponseResult},
     rpc_handler::RpcHandler,
 };
-use starknet_devnet_types::starknet_api::block::BlockNumber;
-use std::ops::DerefMut;
-use std::sync::Arc;
-use tokio::sync::Mutex;
 
-use crate::js_callback::JsCallbackHolder;
+use crate::data_feed::DataFeed;
+use crate::errors::Result;
 
 #[derive(Clone)]
 pub struct JsonRpcWrapper {
     json_rpc_handler: JsonRpcHandler,
-    // TODO: alter
-    js_callback: Arc<Mutex<JsCallbackHolder<serde_json::Value>>>,
-    block_number: Arc<Mutex<BlockNumber>>,
+    data_feed: DataFeed,
 }
 
 impl JsonRpcWrapper {
-    pub fn new(json_rpc_handler: JsonRpcHandler, js_callback: JsCallbackHolder<serde_json::Value>) -> Self {
+    pub fn new(json_rpc_handler: JsonRpcHandler, data_feed: DataFeed) -> Self {
         Self {
             json_rpc_handler,
-            js_callback: Arc::new(Mutex::new(js_callback)),
-            block_number: Arc::new(Mutex::new(BlockNumber(u64::MAX))),
+            data_feed,
         }
     }
 
-    async fn send_block(&self, block_number: BlockNumber) {
-        let block = self
-            .json_rpc_handler
-            .on_request(<JsonRpcWrapper as RpcHandler>::Request::BlockWithFullTransactions(
-                BlockIdInput {
-                    block_id: starknet_core::types::BlockId::Number(block_number.0).into(),
-                },
-            ))
-            .await;
+    /// Handles a call made from JS in-process the same way as one that came over HTTP
+    pub async fn request(&self, method: &str, params: Option<serde_json::Value>) -> Result<serde_json::Value> {
+        let request = Self::parse_request(method, params)?;
+        Self::into_result(self.on_request(request).await)
+    }
+
+    /// Like [Self::request], for calls that can't change state, so the data feed is left alone
+    pub async fn read(&self, request: <Self as RpcHandler>::Request) -> Result<serde_json::Value> {
+        Self::into_result(self.json_rpc_handler.on_request(request).await)
+    }
 
-        let calldata = match block {
-            ResponseResult::Success(val) => val,
-            ResponseResult::Error(err) => {
-                // TODO: change callback to have 2 argument. Error and Response
-                serde_json::to_value(err).unwrap()
+    fn parse_request(method: &str, params: Option<serde_json::Value>) -> Result<<Self as RpcHandler>::Request> {
+        let mut call = serde_json::Map::new();
+        call.insert("method".into(), method.into());
+        if let Some(params) = params {
+            call.insert("params".into(), params);
+        }
+
+        let request = serde_json::from_value::<<Self as RpcHandler>::Request>(call.into()).map_err(|err| {
+            // Same mapping as the HTTP route uses
+            let err = err.to_string();
+            if err.contains("unknown variant") {
+                RpcError::method_not_found()
+            } else {
+                RpcError::invalid_params(err)
             }
-        };
+        })?;
 
-        self.js_callback.lock().await.deref_mut().call(calldata);
+        Ok(request)
+    }
+
+    fn into_result(response: ResponseResult) -> Result<serde_json::Value> {
+        match response {
+            ResponseResult::Success(value) => Ok(value),
+            ResponseResult::Error(err) => Err(err.into()),
+        }
     }
 }
 
@@ -55,19 +66,7 @@ impl RpcHandler for JsonRpcWrapper {
 
     async fn on_request(&self, request: Self::Request) -> ResponseResult {
         let response = self.json_rpc_handler.on_request(request).await;
-
-        let latest_block = self.json_rpc_handler.api.starknet.read().await.get_latest_block();
-        let latest_block_number = match latest_block {
-            Ok(block) => block.block_number(),
-            // No blocks yet. Return response
-            Err(_) => return response,
-        };
-
-        let mut block_number = self.block_number.lock().await;
-        if *block_number != latest_block_number {
-            *block_number = latest_block_number;
-            self.send_block(latest_block_number).await;
-        }
+        self.data_feed.sync().await;
 
         response
     }
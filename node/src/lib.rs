@@ -1,11 +1,18 @@
 use neon::prelude::*;
 
+mod admin_api;
+mod config_validation;
+mod data_feed;
 mod devnet_adapter;
+mod devnet_instance;
 mod errors;
 mod js_callback;
 mod js_traits;
 mod json_rpc_wrapper;
+mod read_api;
+mod registry;
 mod server_builder;
+mod snapshots;
 mod types;
 
 register_module!(mut cx, {
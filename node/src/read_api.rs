@@ -0,0 +1,108 @@
+use serde::Deserialize;
+use snafu::OptionExt;
+use starknet_core::types::BlockTag;
+use starknet_devnet_server::{
+    api::json_rpc::{
+        models::{
+            BlockAndClassHashInput, BlockAndContractAddressInput, BlockIdInput, GetStorageInput, TransactionHashInput,
+        },
+        JsonRpcHandler,
+    },
+    rpc_handler::RpcHandler,
+};
+use starknet_devnet_types::{
+    contract_address::ContractAddress,
+    felt::{ClassHash, TransactionHash},
+    patricia_key::PatriciaKey,
+    rpc::block::BlockId,
+};
+
+use crate::errors::{InvalidArgumentSnafu, InvalidArgumentValueSnafu, Result};
+
+type Request = <JsonRpcHandler as RpcHandler>::Request;
+
+/// Read-only queries with typed arguments, each backed by a JSON-RPC method
+#[derive(Clone, Copy)]
+pub enum ReadMethod {
+    GetBlock,
+    GetTransaction,
+    GetTransactionReceipt,
+    GetStateUpdate,
+    GetClass,
+    GetNonce,
+    GetStorageAt,
+}
+
+/// Arguments of every read method, each uses a subset of them
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadArgs {
+    // Latest block if missing
+    block_id: Option<BlockId>,
+    transaction_hash: Option<TransactionHash>,
+    class_hash: Option<ClassHash>,
+    contract_address: Option<ContractAddress>,
+    key: Option<PatriciaKey>,
+}
+
+impl ReadArgs {
+    pub fn parse(args: serde_json::Value) -> Result<Self> {
+        serde_json::from_value(args).map_err(|err| {
+            InvalidArgumentValueSnafu {
+                name: "args",
+                message: err.to_string(),
+            }
+            .build()
+        })
+    }
+}
+
+impl ReadMethod {
+    pub fn parse(method: &str) -> Option<Self> {
+        match method {
+            "getBlock" => Some(ReadMethod::GetBlock),
+            "getTransaction" => Some(ReadMethod::GetTransaction),
+            "getTransactionReceipt" => Some(ReadMethod::GetTransactionReceipt),
+            "getStateUpdate" => Some(ReadMethod::GetStateUpdate),
+            "getClass" => Some(ReadMethod::GetClass),
+            "getNonce" => Some(ReadMethod::GetNonce),
+            "getStorageAt" => Some(ReadMethod::GetStorageAt),
+            _ => None,
+        }
+    }
+
+    /// Request for the JSON-RPC handler, the same one the HTTP route would deserialize
+    pub fn into_request(self, args: ReadArgs) -> Result<Request> {
+        let block_id = args
+            .block_id
+            .unwrap_or_else(|| starknet_core::types::BlockId::Tag(BlockTag::Latest).into());
+
+        Ok(match self {
+            ReadMethod::GetBlock => Request::BlockWithFullTransactions(BlockIdInput { block_id }),
+            ReadMethod::GetTransaction => Request::TransactionByHash(TransactionHashInput {
+                transaction_hash: required(args.transaction_hash, "transactionHash")?,
+            }),
+            ReadMethod::GetTransactionReceipt => Request::TransactionReceiptByTransactionHash(TransactionHashInput {
+                transaction_hash: required(args.transaction_hash, "transactionHash")?,
+            }),
+            ReadMethod::GetStateUpdate => Request::StateUpdate(BlockIdInput { block_id }),
+            ReadMethod::GetClass => Request::ClassByHash(BlockAndClassHashInput {
+                block_id,
+                class_hash: required(args.class_hash, "classHash")?,
+            }),
+            ReadMethod::GetNonce => Request::ContractNonce(BlockAndContractAddressInput {
+                block_id,
+                contract_address: required(args.contract_address, "contractAddress")?,
+            }),
+            ReadMethod::GetStorageAt => Request::StorageAt(GetStorageInput {
+                contract_address: required(args.contract_address, "contractAddress")?,
+                key: required(args.key, "key")?,
+                block_id,
+            }),
+        })
+    }
+}
+
+fn required<T>(value: Option<T>, name: &'static str) -> Result<T> {
+    value.context(InvalidArgumentSnafu { name })
+}
@@ -0,0 +1,152 @@
+use neon::prelude::*;
+use neon::types::Finalize;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::thread::ThreadId;
+use std::time::Duration;
+
+use crate::{devnet_instance::DevnetInstance, types::DevnetStartResult};
+
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InstanceStatus {
+    Running,
+    Stopping,
+}
+
+/// Summary of a registered instance as listed to JS
+#[derive(Clone, Serialize)]
+pub struct InstanceInfo {
+    pub id: u32,
+    // None in headless mode
+    pub port: Option<u16>,
+    // Config as it was passed from JS
+    pub config: serde_json::Value,
+    pub status: InstanceStatus,
+}
+
+struct RegistryEntry {
+    info: InstanceInfo,
+    start_result: DevnetStartResult,
+    // JS thread of the Node environment that started the instance
+    env_thread: ThreadId,
+}
+
+/// Devnet instances of the process that haven't exited yet, by id
+pub struct InstanceRegistry {
+    next_id: AtomicU32,
+    entries: Mutex<BTreeMap<u32, RegistryEntry>>,
+}
+
+pub static REGISTRY: InstanceRegistry = InstanceRegistry::new();
+
+impl InstanceRegistry {
+    const fn new() -> Self {
+        Self {
+            next_id: AtomicU32::new(1),
+            entries: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    pub fn next_id(&self) -> u32 {
+        self.next_id.fetch_add(1, Ordering::SeqCst)
+    }
+
+    pub fn register(&self, start_result: DevnetStartResult, config: serde_json::Value, env_thread: ThreadId) {
+        let info = InstanceInfo {
+            id: start_result.id,
+            port: start_result.address.map(|address| address.port()),
+            config,
+            status: InstanceStatus::Running,
+        };
+        self.lock().insert(
+            info.id,
+            RegistryEntry {
+                info,
+                start_result,
+                env_thread,
+            },
+        );
+    }
+
+    /// Called once the server thread of the instance is done
+    pub fn unregister(&self, id: u32) {
+        self.lock().remove(&id);
+    }
+
+    pub fn set_status(&self, id: u32, status: InstanceStatus) {
+        if let Some(entry) = self.lock().get_mut(&id) {
+            entry.info.status = status;
+        }
+    }
+
+    pub fn list(&self) -> Vec<InstanceInfo> {
+        self.lock().values().map(|entry| entry.info.clone()).collect()
+    }
+
+    /// Returns the instance as it was handed over on start
+    pub fn get(&self, id: u32) -> Option<DevnetStartResult> {
+        self.lock().get(&id).map(|entry| entry.start_result.clone())
+    }
+
+    pub fn instances(&self) -> Vec<DevnetInstance> {
+        self.lock()
+            .values()
+            .map(|entry| entry.start_result.instance.clone())
+            .collect()
+    }
+
+    /// Instances started from the Node environment running on `env_thread`
+    pub fn instances_started_on(&self, env_thread: ThreadId) -> Vec<DevnetInstance> {
+        self.lock()
+            .values()
+            .filter(|entry| entry.env_thread == env_thread)
+            .map(|entry| entry.start_result.instance.clone())
+            .collect()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, BTreeMap<u32, RegistryEntry>> {
+        self.entries.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// How long a torn down environment waits for instances that dump on exit, before leaving them behind
+const DUMP_ON_EXIT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Lives as long as the Node environment that loaded the module.
+/// N-API finalizes it when the environment is torn down, be it on process exit or when a worker is terminated
+struct EnvGuard {
+    // JS thread of the environment, which is what instances are registered with
+    env_thread: ThreadId,
+}
+
+impl Finalize for EnvGuard {
+    fn finalize<'a, C: Context<'a>>(self, _: &mut C) {
+        stop_env_instances(self.env_thread);
+    }
+}
+
+/// Stops the instances started from the Node environment of `cx` once the environment is torn down.
+/// This releases their ports even if JS never stopped them
+pub fn guard_env(cx: &mut ModuleContext) -> NeonResult<()> {
+    let guard = cx.boxed(EnvGuard {
+        env_thread: std::thread::current().id(),
+    });
+
+    // Kept alive by the module exports, which live as long as the environment
+    cx.export_value("__envGuard", guard)
+}
+
+fn stop_env_instances(env_thread: ThreadId) {
+    let instances = REGISTRY.instances_started_on(env_thread);
+    for instance in &instances {
+        instance.shutdown();
+    }
+
+    // The others are not joined, waiting for every server to shut down gracefully would stall the teardown
+    for instance in instances.iter().filter(|instance| instance.dumps_on_exit()) {
+        instance.wait_for_exit(DUMP_ON_EXIT_TIMEOUT);
+    }
+}
@@ -1,4 +1,10 @@
-use axum::routing::{get, post};
+use axum::{
+    body::Body,
+    http::Request,
+    middleware::{self, Next},
+    routing::{get, post, MethodRouter},
+};
+use serde::Serialize;
 use starknet_devnet_core::starknet::starknet_config::StarknetConfig;
 use starknet_devnet_server::{
     api::http::endpoints as http,
@@ -8,15 +14,53 @@ use starknet_devnet_server::{
 };
 use std::net::SocketAddr;
 
+use crate::data_feed::{ChainResetReason, DataFeed};
 use crate::errors::Result;
 
-/// Configures an [axum::Server] that handles related JSON-RPC calls and WEB API calls via HTTP
+/// A port that couldn't be bound, with the reason
+#[derive(Clone, Debug, Serialize)]
+pub struct PortAttempt {
+    pub port: u16,
+    pub message: String,
+}
+
+/// Syncs the data feed once the wrapped route has been handled, so state changes made through the
+/// HTTP API are reported right away
+fn with_data_feed(route: MethodRouter, data_feed: &DataFeed) -> MethodRouter {
+    let data_feed = data_feed.clone();
+    route.layer(middleware::from_fn(move |request: Request<Body>, next: Next<Body>| {
+        let data_feed = data_feed.clone();
+        async move {
+            let response = next.run(request).await;
+            data_feed.sync().await;
+
+            response
+        }
+    }))
+}
+
+/// Resets the data feed once the wrapped route has replaced the devnet state successfully
+fn with_chain_reset(route: MethodRouter, data_feed: &DataFeed, reason: ChainResetReason) -> MethodRouter {
+    let data_feed = data_feed.clone();
+    route.layer(middleware::from_fn(move |request: Request<Body>, next: Next<Body>| {
+        let data_feed = data_feed.clone();
+        async move {
+            data_feed
+                .reset_with(reason, next.run(request), |response| response.status().is_success())
+                .await
+        }
+    }))
+}
+
+/// Configures an [axum::Server] that handles related JSON-RPC calls and WEB API calls via HTTP.
+/// The listener is bound right away, if the port of `addr` is 0 the one picked by the OS is in `local_addr`
 pub fn serve_http_api_json_rpc<TJsonRpcHandler: RpcHandler, THttpApiHandler: Clone + Send + Sync + 'static>(
     addr: SocketAddr,
     config: ServerConfig,
     json_rpc_handler: TJsonRpcHandler,
     http_api_handler: THttpApiHandler,
     starknet_config: &StarknetConfig,
+    data_feed: &DataFeed,
 ) -> Result<StarknetDevnetServer> {
     Ok(builder::Builder::new(addr, json_rpc_handler, http_api_handler)
         .set_config(config)
@@ -24,26 +68,47 @@ pub fn serve_http_api_json_rpc<TJsonRpcHandler: RpcHandler, THttpApiHandler: Clo
         .json_rpc_route("/rpc")
         .http_api_route("/is_alive", get(http::is_alive))
         .http_api_route("/dump", post(http::dump_load::dump))
-        .http_api_route("/load", post(http::dump_load::load))
-        .http_api_route("/postman/load_l1_messaging_contract", post(http::postman::postman_load))
-        .http_api_route("/postman/flush", post(http::postman::postman_flush))
+        .http_api_route(
+            "/load",
+            with_chain_reset(post(http::dump_load::load), data_feed, ChainResetReason::Load),
+        )
+        .http_api_route(
+            "/postman/load_l1_messaging_contract",
+            with_data_feed(post(http::postman::postman_load), data_feed),
+        )
+        .http_api_route(
+            "/postman/flush",
+            with_data_feed(post(http::postman::postman_flush), data_feed),
+        )
         .http_api_route(
             "/postman/send_message_to_l2",
-            post(http::postman::postman_send_message_to_l2),
+            with_data_feed(post(http::postman::postman_send_message_to_l2), data_feed),
         )
         .http_api_route(
             "/postman/consume_message_from_l2",
-            post(http::postman::postman_consume_message_from_l2),
+            with_data_feed(post(http::postman::postman_consume_message_from_l2), data_feed),
+        )
+        .http_api_route(
+            "/create_block",
+            with_data_feed(post(http::blocks::create_block), data_feed),
+        )
+        .http_api_route(
+            "/abort_blocks",
+            with_data_feed(post(http::blocks::abort_blocks), data_feed),
+        )
+        .http_api_route(
+            "/restart",
+            with_chain_reset(post(http::restart), data_feed, ChainResetReason::Restart),
+        )
+        .http_api_route("/set_time", with_data_feed(post(http::time::set_time), data_feed))
+        .http_api_route(
+            "/increase_time",
+            with_data_feed(post(http::time::increase_time), data_feed),
         )
-        .http_api_route("/create_block", post(http::blocks::create_block))
-        .http_api_route("/abort_blocks", post(http::blocks::abort_blocks))
-        .http_api_route("/restart", post(http::restart))
-        .http_api_route("/set_time", post(http::time::set_time))
-        .http_api_route("/increase_time", post(http::time::increase_time))
         .http_api_route("/predeployed_accounts", get(http::accounts::get_predeployed_accounts))
         .http_api_route("/account_balance", get(http::accounts::get_account_balance))
         .http_api_route("/fee_token", get(http::mint_token::get_fee_token))
-        .http_api_route("/mint", post(http::mint_token::mint))
+        .http_api_route("/mint", with_data_feed(post(http::mint_token::mint), data_feed))
         .http_api_route("/fork_status", get(http::get_fork_status))
         .build(starknet_config)?)
 }
@@ -0,0 +1,98 @@
+use serde::Serialize;
+use snafu::OptionExt;
+use starknet_devnet_core::starknet::Starknet;
+use starknet_devnet_server::api::Api;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use crate::data_feed::{ChainResetReason, DataFeed};
+use crate::errors::{Result, SnapshotNotFoundSnafu};
+
+/// A saved devnet state that can be reverted to
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotInfo {
+    pub id: u32,
+    // Latest block when the snapshot was taken
+    pub block_number: Option<u64>,
+}
+
+struct Snapshot {
+    info: SnapshotInfo,
+    starknet: Starknet,
+}
+
+#[derive(Default)]
+struct SnapshotStore {
+    last_id: u32,
+    snapshots: BTreeMap<u32, Snapshot>,
+}
+
+/// Snapshots of one devnet instance, kept as in-memory copies of the whole Starknet state
+#[derive(Clone)]
+pub struct Snapshots {
+    api: Api,
+    data_feed: DataFeed,
+    // Async, so that a snapshot or revert in progress finishes before the next one starts
+    store: Arc<Mutex<SnapshotStore>>,
+}
+
+impl Snapshots {
+    pub fn new(api: Api, data_feed: DataFeed) -> Self {
+        Self {
+            api,
+            data_feed,
+            store: Default::default(),
+        }
+    }
+
+    pub async fn take(&self) -> Result<SnapshotInfo> {
+        let mut store = self.store.lock().await;
+        let id = store.last_id + 1;
+
+        let starknet = self.api.starknet.read().await.clone();
+        let info = SnapshotInfo {
+            id,
+            block_number: starknet.get_latest_block().ok().map(|block| block.block_number().0),
+        };
+
+        store.last_id = id;
+        store.snapshots.insert(
+            id,
+            Snapshot {
+                info: info.clone(),
+                starknet,
+            },
+        );
+
+        Ok(info)
+    }
+
+    /// Replaces the chain with the one saved in the snapshot, which stays available for further reverts
+    pub async fn revert(&self, id: u32) -> Result<()> {
+        let store = self.store.lock().await;
+        let snapshot = store.snapshots.get(&id).context(SnapshotNotFoundSnafu { id })?;
+
+        let swap = async {
+            *self.api.starknet.write().await = snapshot.starknet.clone();
+        };
+        self.data_feed
+            .reset_with(ChainResetReason::Revert, swap, |_| true)
+            .await;
+
+        Ok(())
+    }
+
+    pub async fn list(&self) -> Vec<SnapshotInfo> {
+        let store = self.store.lock().await;
+        store.snapshots.values().map(|snapshot| snapshot.info.clone()).collect()
+    }
+
+    pub async fn delete(&self, id: u32) -> Result<()> {
+        let mut store = self.store.lock().await;
+        store.snapshots.remove(&id).context(SnapshotNotFoundSnafu { id })?;
+
+        Ok(())
+    }
+}
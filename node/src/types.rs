@@ -1,63 +1,409 @@
 use neon::context::Context;
 use neon::handle::Handle;
 use neon::object::Object;
-use neon::prelude::{JsNumber, JsObject, JsResultExt, NeonResult};
-use neon::types::{JsValue, Value};
+use neon::prelude::{JsBuffer, JsObject, NeonResult};
+use neon::types::JsValue;
 use serde::Serialize;
+use starknet_devnet_core::starknet::starknet_config::{DumpOn, StarknetConfig};
+use starknet_devnet_types::{
+    chain_id::ChainId,
+    contract_class::ContractClass,
+    felt::{Balance, ClassHash},
+    traits::HashProducer,
+};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
 
 use crate::{
-    errors::Result,
-    js_traits::{FromJsValue, IntoJsType, IntoJsTypeBlanket, JsArraySerializedTypeProxy, JsonValueTypeProxy},
+    config_validation::{ConfigValidator, RawValue, MAX_SAFE_INTEGER},
+    data_feed::{FeedFormat, FeedOptions, FeedOverflow, FeedPayload, SequencedEvent},
+    devnet_instance::DevnetInstance,
+    errors::{Error, Result},
+    js_traits::{
+        BoxedResultProxy, FromJsValue, IntoJsType, IntoJsTypeBlanket, IntoJsTypeProxy, JsArraySerializedTypeProxy,
+        JsonValueTypeProxy, PromisifiedJsTypeProxy,
+    },
+    registry::InstanceInfo,
+    snapshots::SnapshotInfo,
 };
 
 pub struct DevnetConfig {
     pub seed: u32,
     pub total_accounts: u8,
+    // No HTTP server is started, the instance is only reachable through in-process calls
+    pub headless: bool,
     pub port: u16,
+    // Number of successive ports that may be tried after `port` if it's busy
+    pub auto_port: Option<u16>,
+    pub host: Option<IpAddr>,
+    pub chain_id: Option<ChainId>,
+    pub initial_balance: Option<Balance>,
+    pub gas_price: Option<u64>,
+    pub start_time: Option<u64>,
+    pub timeout: Option<u16>,
+    // Transactions are kept pending until a block is created explicitly
+    pub blocks_on_demand: bool,
+    pub account_class_path: Option<String>,
+    // Read from accountClassPath while validating, along with its hash
+    pub account_class: Option<(ContractClass, ClassHash)>,
+    pub dump_on: Option<DumpOn>,
+    pub dump_path: Option<String>,
+    pub feed_queue_limit: Option<usize>,
+    pub feed_overflow: Option<FeedOverflow>,
+    pub feed_format: Option<FeedFormat>,
+    pub feed_replay_capacity: Option<usize>,
+}
+
+impl DevnetConfig {
+    /// Ports to try binding in order, none in headless mode
+    pub fn candidate_ports(&self) -> Vec<u16> {
+        if self.headless {
+            return vec![];
+        }
+
+        match (self.port, self.auto_port) {
+            // Always succeeds unless the host is unavailable
+            (0, _) | (_, None) => vec![self.port],
+            (port, Some(auto_port)) => (port..=port.saturating_add(auto_port)).collect(),
+        }
+    }
+
+    pub fn feed_options(&self) -> FeedOptions {
+        FeedOptions {
+            queue_limit: self.feed_queue_limit,
+            overflow: self.feed_overflow.unwrap_or_default(),
+            format: self.feed_format.unwrap_or_default(),
+            replay_capacity: self.feed_replay_capacity,
+        }
+    }
+
+    /// Config as listed for running instances, in the shape JS passes it
+    pub fn to_json(&self) -> Result<serde_json::Value> {
+        let mut config = serde_json::Map::new();
+        config.insert("seed".into(), self.seed.into());
+        config.insert("port".into(), self.port.into());
+        config.insert("totalAccounts".into(), self.total_accounts.into());
+        // Flags are only listed when set, like they are usually passed
+        if self.headless {
+            config.insert("headless".into(), true.into());
+        }
+        if self.blocks_on_demand {
+            config.insert("blocksOnDemand".into(), true.into());
+        }
+
+        let initial_balance = match &self.initial_balance {
+            Some(initial_balance) => Some(serde_json::to_value(initial_balance)?),
+            None => None,
+        };
+        let optional_fields = [
+            ("autoPort", self.auto_port.map(serde_json::Value::from)),
+            ("host", self.host.map(|host| host.to_string().into())),
+            (
+                "chainId",
+                self.chain_id.as_ref().map(|chain_id| chain_id_name(chain_id).into()),
+            ),
+            ("initialBalance", initial_balance),
+            ("gasPrice", self.gas_price.map(serde_json::Value::from)),
+            ("startTime", self.start_time.map(serde_json::Value::from)),
+            ("requestTimeout", self.timeout.map(serde_json::Value::from)),
+            (
+                "accountClassPath",
+                self.account_class_path.clone().map(serde_json::Value::from),
+            ),
+            (
+                "dumpOn",
+                self.dump_on.as_ref().map(|dump_on| dump_on_name(dump_on).into()),
+            ),
+            ("dumpPath", self.dump_path.clone().map(serde_json::Value::from)),
+            ("feedQueueLimit", self.feed_queue_limit.map(serde_json::Value::from)),
+            (
+                "feedOverflow",
+                self.feed_overflow.map(|overflow| feed_overflow_name(overflow).into()),
+            ),
+            (
+                "feedFormat",
+                self.feed_format.map(|format| feed_format_name(format).into()),
+            ),
+            (
+                "feedReplayCapacity",
+                self.feed_replay_capacity.map(serde_json::Value::from),
+            ),
+        ];
+        for (field, value) in optional_fields {
+            if let Some(value) = value {
+                config.insert(field.into(), value);
+            }
+        }
+
+        Ok(config.into())
+    }
+}
+
+fn parse_host(host: &str) -> Option<IpAddr> {
+    match host {
+        "localhost" => Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+        host => host.parse().ok(),
+    }
+}
+
+fn parse_chain_id(chain_id: &str) -> Option<ChainId> {
+    match chain_id {
+        "MAINNET" => Some(ChainId::Mainnet),
+        "TESTNET" => Some(ChainId::Testnet),
+        _ => None,
+    }
+}
+
+fn chain_id_name(chain_id: &ChainId) -> &'static str {
+    match chain_id {
+        ChainId::Mainnet => "MAINNET",
+        ChainId::Testnet => "TESTNET",
+    }
+}
+
+fn parse_balance(balance: &str) -> Option<Balance> {
+    if balance.starts_with("0x") {
+        Balance::from_prefixed_hex_str(balance).ok()
+    } else {
+        balance.parse::<u128>().ok().map(Balance::from)
+    }
+}
+
+fn parse_feed_overflow(feed_overflow: &str) -> Option<FeedOverflow> {
+    match feed_overflow {
+        "drop" => Some(FeedOverflow::Drop),
+        "coalesce" => Some(FeedOverflow::Coalesce),
+        _ => None,
+    }
+}
+
+fn feed_overflow_name(feed_overflow: FeedOverflow) -> &'static str {
+    match feed_overflow {
+        FeedOverflow::Drop => "drop",
+        FeedOverflow::Coalesce => "coalesce",
+    }
+}
+
+fn parse_feed_format(feed_format: &str) -> Option<FeedFormat> {
+    match feed_format {
+        "object" => Some(FeedFormat::Object),
+        "json" => Some(FeedFormat::Json),
+        "buffer" => Some(FeedFormat::Buffer),
+        _ => None,
+    }
+}
+
+fn feed_format_name(feed_format: FeedFormat) -> &'static str {
+    match feed_format {
+        FeedFormat::Object => "object",
+        FeedFormat::Json => "json",
+        FeedFormat::Buffer => "buffer",
+    }
+}
+
+/// Reads a Sierra account class, failing with the message reported for the config field
+fn read_account_class(path: &str) -> std::result::Result<(ContractClass, ClassHash), String> {
+    let sierra = std::fs::read_to_string(path).map_err(|err| format!("can't be read: {}", err))?;
+    let account_class = ContractClass::cairo_1_from_sierra_json_str(&sierra)
+        .map(ContractClass::Cairo1)
+        .map_err(|err| format!("is not a valid Sierra class: {}", err))?;
+    let class_hash = account_class
+        .generate_hash()
+        .map_err(|err| format!("can't be hashed: {}", err))?;
+
+    Ok((account_class, class_hash))
+}
+
+fn parse_dump_on(dump_on: &str) -> Option<DumpOn> {
+    match dump_on {
+        "exit" => Some(DumpOn::Exit),
+        "transaction" => Some(DumpOn::Transaction),
+        _ => None,
+    }
+}
+
+fn dump_on_name(dump_on: &DumpOn) -> &'static str {
+    match dump_on {
+        DumpOn::Exit => "exit",
+        DumpOn::Transaction => "transaction",
+    }
 }
 
 impl FromJsValue for DevnetConfig {
-    type Output = Self;
+    type Output = Result<Self>;
 
     fn from_js_value<'a, C: Context<'a>>(cx: &mut C, object: Handle<'a, JsObject>) -> NeonResult<Self::Output> {
-        let seed = object
-            .get(cx, "seed")?
-            .downcast::<JsNumber, _>(cx)
-            .or_throw(cx)?
-            .value(cx) as u32;
-        let total_accounts = object
-            .get(cx, "totalAccounts")?
-            .downcast::<JsNumber, _>(cx)
-            .or_throw(cx)?
-            .value(cx) as u8;
-        let port = object
-            .get(cx, "port")?
-            .downcast::<JsNumber, _>(cx)
-            .or_throw(cx)?
-            .value(cx) as u16;
-
-        Ok(Self {
+        let mut validator = ConfigValidator::default();
+
+        let seed = validator.required_integer("seed", RawValue::read(cx, object, "seed")?, 0..=u32::MAX as u64);
+        let total_accounts = validator.required_integer(
+            "totalAccounts",
+            RawValue::read(cx, object, "totalAccounts")?,
+            1..=u8::MAX as u64,
+        );
+        let headless = validator
+            .optional_bool("headless", RawValue::read(cx, object, "headless")?)
+            .unwrap_or_default();
+        // 0 lets the OS pick a free port
+        let port = match headless {
+            // Ignored, there is nothing to bind
+            true => validator
+                .optional_integer("port", RawValue::read(cx, object, "port")?, 0..=u16::MAX as u64)
+                .unwrap_or_default(),
+            false => validator.required_integer("port", RawValue::read(cx, object, "port")?, 0..=u16::MAX as u64),
+        };
+
+        let auto_port =
+            validator.optional_integer("autoPort", RawValue::read(cx, object, "autoPort")?, 1..=u16::MAX as u64);
+
+        let host = validator.optional_parsed(
+            "host",
+            RawValue::read(cx, object, "host")?,
+            "an IP address or 'localhost'",
+            parse_host,
+        );
+        let chain_id = validator.optional_parsed(
+            "chainId",
+            RawValue::read(cx, object, "chainId")?,
+            "'MAINNET' or 'TESTNET'",
+            parse_chain_id,
+        );
+        let initial_balance = validator.optional_parsed(
+            "initialBalance",
+            RawValue::read(cx, object, "initialBalance")?,
+            "a decimal or 0x-prefixed hex string",
+            parse_balance,
+        );
+        let gas_price = validator.optional_integer(
+            "gasPrice",
+            RawValue::read(cx, object, "gasPrice")?,
+            1..=MAX_SAFE_INTEGER,
+        );
+        let start_time = validator.optional_integer(
+            "startTime",
+            RawValue::read(cx, object, "startTime")?,
+            0..=MAX_SAFE_INTEGER,
+        );
+        let timeout = validator.optional_integer(
+            "requestTimeout",
+            RawValue::read(cx, object, "requestTimeout")?,
+            1..=u16::MAX as u64,
+        );
+        let blocks_on_demand = validator
+            .optional_bool("blocksOnDemand", RawValue::read(cx, object, "blocksOnDemand")?)
+            .unwrap_or_default();
+        let account_class_path =
+            validator.optional_string("accountClassPath", RawValue::read(cx, object, "accountClassPath")?);
+        let account_class = account_class_path
+            .as_deref()
+            .and_then(|path| match read_account_class(path) {
+                Ok(account_class) => Some(account_class),
+                Err(message) => {
+                    validator.invalid("accountClassPath", message);
+                    None
+                }
+            });
+        let dump_on = validator.optional_parsed(
+            "dumpOn",
+            RawValue::read(cx, object, "dumpOn")?,
+            "'exit' or 'transaction'",
+            parse_dump_on,
+        );
+        let dump_path = validator.optional_string("dumpPath", RawValue::read(cx, object, "dumpPath")?);
+
+        let feed_queue_limit = validator.optional_integer(
+            "feedQueueLimit",
+            RawValue::read(cx, object, "feedQueueLimit")?,
+            1..=u32::MAX as u64,
+        );
+        let feed_overflow = validator.optional_parsed(
+            "feedOverflow",
+            RawValue::read(cx, object, "feedOverflow")?,
+            "'drop' or 'coalesce'",
+            parse_feed_overflow,
+        );
+        let feed_format = validator.optional_parsed(
+            "feedFormat",
+            RawValue::read(cx, object, "feedFormat")?,
+            "'object', 'json' or 'buffer'",
+            parse_feed_format,
+        );
+        let feed_replay_capacity = validator.optional_integer(
+            "feedReplayCapacity",
+            RawValue::read(cx, object, "feedReplayCapacity")?,
+            0..=u32::MAX as u64,
+        );
+
+        if dump_on.is_some() && dump_path.is_none() {
+            validator.invalid("dumpPath", "is required when dumpOn is set");
+        }
+
+        if let Err(err) = validator.finish() {
+            return Ok(Err(err));
+        }
+
+        Ok(Ok(Self {
             seed,
             total_accounts,
+            headless,
             port,
-        })
+            auto_port,
+            host,
+            chain_id,
+            initial_balance,
+            gas_price,
+            start_time,
+            timeout,
+            blocks_on_demand,
+            account_class_path,
+            account_class,
+            dump_on,
+            dump_path,
+            feed_queue_limit,
+            feed_overflow,
+            feed_format,
+            feed_replay_capacity,
+        }))
     }
 }
 
-impl Into<starknet_devnet_core::starknet::starknet_config::StarknetConfig> for DevnetConfig {
-    fn into(self) -> starknet_devnet_core::starknet::starknet_config::StarknetConfig {
-        use starknet_devnet_core::starknet::starknet_config::StarknetConfig;
+impl TryFrom<DevnetConfig> for StarknetConfig {
+    type Error = Error;
 
+    fn try_from(value: DevnetConfig) -> Result<Self> {
         let mut config = StarknetConfig::default();
-        config.port = self.port;
-        config.seed = self.seed;
-        config.total_accounts = self.total_accounts;
+        config.port = value.port;
+        config.seed = value.seed;
+        config.total_accounts = value.total_accounts;
 
-        config
+        if let Some(host) = value.host {
+            config.host = host;
+        }
+        if let Some(chain_id) = value.chain_id {
+            config.chain_id = chain_id;
+        }
+        if let Some(initial_balance) = value.initial_balance {
+            config.predeployed_accounts_initial_balance = initial_balance;
+        }
+        if let Some(gas_price) = value.gas_price {
+            config.gas_price = gas_price;
+        }
+        if let Some(timeout) = value.timeout {
+            config.timeout = timeout;
+        }
+        if let Some((account_class, class_hash)) = value.account_class {
+            config.account_contract_class_hash = class_hash;
+            config.account_contract_class = account_class;
+        }
+        config.start_time = value.start_time;
+        config.blocks_on_demand = value.blocks_on_demand;
+        config.dump_on = value.dump_on;
+        config.dump_path = value.dump_path;
+
+        Ok(config)
     }
 }
 
-#[derive(Serialize)]
+#[derive(Clone, Serialize)]
 pub(crate) struct AccountData {
     pub account_address: starknet_devnet_types::contract_address::ContractAddress,
     pub public_key: starknet_devnet_types::felt::Key,
@@ -86,19 +432,118 @@ impl IntoJsTypeBlanket for serde_json::Value {
     type Proxy = JsonValueTypeProxy<serde_json::Value>;
 }
 
-impl IntoJsType for Result<Vec<AccountData>> {
+// Register type
+impl IntoJsTypeBlanket for SequencedEvent {
+    type Proxy = JsonValueTypeProxy<SequencedEvent>;
+}
+
+impl IntoJsType for FeedPayload {
     type JsType = JsValue;
     fn into_js_type<'a, C>(self, cx: &mut C) -> Result<Vec<Handle<'a, Self::JsType>>>
     where
         C: Context<'a>,
     {
-        match self {
-            Ok(val) => {
-                let mut res = vec![cx.undefined().as_value(cx)];
-                res.extend(&val.into_js_type(cx)?);
-                Ok(res)
+        let payload = match self {
+            FeedPayload::Object(event) => neon_serde2::to_value(cx, event.as_ref())?,
+            FeedPayload::Json(json) => cx.string(json).upcast(),
+            FeedPayload::Buffer(bytes) => {
+                // Copied rather than external, Electron doesn't allow buffers pointing outside of the V8 heap
+                let mut buffer = JsBuffer::new(cx, bytes.len() as u32)?;
+                cx.borrow_mut(&mut buffer, |data| data.as_mut_slice::<u8>().copy_from_slice(&bytes));
+                buffer.upcast()
             }
-            Err(err) => Err(err),
+        };
+
+        Ok(vec![payload])
+    }
+}
+
+// Register type
+impl IntoJsTypeBlanket for Result<FeedPayload> {
+    type Proxy = PromisifiedJsTypeProxy<FeedPayload>;
+}
+
+// Register type
+impl IntoJsTypeBlanket for Vec<InstanceInfo> {
+    type Proxy = JsArraySerializedTypeProxy<InstanceInfo>;
+}
+
+// Register type
+impl IntoJsTypeBlanket for SnapshotInfo {
+    type Proxy = JsonValueTypeProxy<SnapshotInfo>;
+}
+
+// Register type
+impl IntoJsTypeBlanket for Vec<SnapshotInfo> {
+    type Proxy = JsArraySerializedTypeProxy<SnapshotInfo>;
+}
+
+// Register type
+impl IntoJsTypeBlanket for Vec<SequencedEvent> {
+    type Proxy = JsArraySerializedTypeProxy<SequencedEvent>;
+}
+
+#[derive(Clone)]
+pub(crate) struct DevnetStartResult {
+    pub id: u32,
+    pub accounts: Vec<AccountData>,
+    // Actually bound, the configured port may have been 0. None in headless mode
+    pub address: Option<SocketAddr>,
+    pub instance: DevnetInstance,
+}
+
+impl DevnetStartResult {
+    /// HTTP URL of the server, pointing at loopback if it listens on all interfaces
+    pub fn url(&self) -> Option<String> {
+        let address = self.address?;
+        let host = match address.ip() {
+            IpAddr::V4(ip) if ip.is_unspecified() => IpAddr::V4(Ipv4Addr::LOCALHOST),
+            IpAddr::V6(ip) if ip.is_unspecified() => IpAddr::V6(Ipv6Addr::LOCALHOST),
+            ip => ip,
+        };
+
+        Some(format!("http://{}", SocketAddr::new(host, address.port())))
+    }
+}
+
+impl IntoJsType for DevnetStartResult {
+    type JsType = JsValue;
+    fn into_js_type<'a, C>(self, cx: &mut C) -> Result<Vec<Handle<'a, Self::JsType>>>
+    where
+        C: Context<'a>,
+    {
+        let result = cx.empty_object();
+
+        let id = cx.number(self.id);
+        result.set(cx, "id", id)?;
+
+        // Left undefined in headless mode
+        if let (Some(url), Some(address)) = (self.url(), self.address) {
+            let url = cx.string(url);
+            result.set(cx, "url", url)?;
+
+            let port = cx.number(address.port());
+            let address = cx.string(address.to_string());
+            result.set(cx, "address", address)?;
+            result.set(cx, "port", port)?;
         }
+
+        let accounts = self.accounts.into_js_type(cx)?[0];
+        result.set(cx, "accounts", accounts)?;
+
+        let instance = BoxedResultProxy::<DevnetInstance>::into_js_type(self.instance, cx)?[0];
+        result.set(cx, "instance", instance)?;
+
+        Ok(vec![result.upcast()])
+    }
+}
+
+impl IntoJsType for () {
+    type JsType = JsValue;
+    fn into_js_type<'a, C>(self, cx: &mut C) -> Result<Vec<Handle<'a, Self::JsType>>>
+    where
+        C: Context<'a>,
+    {
+        Ok(vec![cx.undefined().upcast()])
     }
 }
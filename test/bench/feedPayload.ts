@@ -0,0 +1,73 @@
+import { Devnet, DevnetConfig, FeedFormat } from 'alpaca-addon';
+import { performance } from 'perf_hooks';
+
+// Compares the JS thread cost of the data feed formats on large blocks. Blocks are created on demand,
+// so that each one holds TRANSACTIONS_PER_BLOCK mints and is delivered with full transactions.
+// Object payloads are stringified afterwards, since that is what forwarding them over IPC costs on top
+// of the neon_serde2::to_value conversion
+const BLOCKS = 10;
+const TRANSACTIONS_PER_BLOCK = 200;
+
+async function measure(feedFormat: FeedFormat): Promise<void> {
+    const config: DevnetConfig = {
+        seed: 20,
+        port: 0,
+        totalAccounts: 1,
+        headless: true,
+        blocksOnDemand: true,
+        feedFormat,
+    };
+
+    let delivered = 0;
+    let bytes = 0;
+    let callbackTime = 0;
+    let onDelivered = () => {};
+
+    const devnet = await Devnet.start(config, (error: unknown, payload?: unknown) => {
+        const started = performance.now();
+        // Whatever would be handed over to IPC
+        const forwarded = typeof payload == 'string' || Buffer.isBuffer(payload) ? payload : JSON.stringify(payload);
+        bytes += forwarded.length;
+        callbackTime += performance.now() - started;
+
+        delivered++;
+        onDelivered();
+    });
+
+    // Genesis block, which isn't measured
+    await new Promise<void>((resolve) => (onDelivered = () => resolve()));
+    bytes = 0;
+    callbackTime = 0;
+
+    const address = devnet.accounts[0].account_address;
+    let jsThreadTime = 0;
+    for (let block = 0; block < BLOCKS; block++) {
+        for (let i = 0; i < TRANSACTIONS_PER_BLOCK; i++) {
+            await devnet.mint({ address, amount: '1000' });
+        }
+
+        const expected = delivered + 1;
+        const blockDelivered = new Promise<void>((resolve) => (onDelivered = () => delivered >= expected && resolve()));
+
+        // Only the event loop time from creating the block until it reaches the callback is counted
+        const utilization = performance.eventLoopUtilization();
+        await devnet.createBlock();
+        await blockDelivered;
+        jsThreadTime += performance.eventLoopUtilization(utilization).active;
+    }
+
+    await devnet.stop();
+
+    console.log(
+        `${feedFormat.padEnd(6)} ${(jsThreadTime / BLOCKS).toFixed(2)} ms of JS thread per block, ` +
+            `${(callbackTime / BLOCKS).toFixed(2)} ms of it in the callback, ${(bytes / BLOCKS).toFixed(0)} bytes per block`
+    );
+}
+
+describe('Data feed payload benchmark', function () {
+    for (const feedFormat of ['object', 'json', 'buffer'] as const) {
+        it(feedFormat, async function () {
+            await measure(feedFormat);
+        });
+    }
+});
@@ -1,4 +1,4 @@
-import { runMochaTests } from './runMochaTests';
+import { runBenchmarks, runMochaTests } from './runMochaTests';
 
 const npsUtils = require('nps-utils');
 const nodeExe = 'node -r ./babel-register.js';
@@ -7,6 +7,7 @@ export default {
     scripts: {
         prepare: `${nodeExe} package-scripts/copyModule.ts`,
         tests: runMochaTests(),
+        bench: runBenchmarks(),
         tsc: npsUtils.series('tsc'),
     },
 };
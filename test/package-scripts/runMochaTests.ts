@@ -10,3 +10,7 @@ const mochaRunner = [
 export function runMochaTests() {
     return `${mochaRunner} tests/test.ts`;
 }
+
+export function runBenchmarks() {
+    return `${mochaRunner} bench/feedPayload.ts`;
+}
@@ -1,15 +1,62 @@
-import { Devnet, DevnetConfig, Error } from 'alpaca-addon';
+import { AccountData, Devnet, DevnetConfig, Error, ErrorType, FeedEvent, ProviderCallback } from 'alpaca-addon';
 import { expect } from 'chai';
+import fs from 'fs';
+import http from 'http';
+import os from 'os';
+import path from 'path';
+import { Worker } from 'worker_threads';
 
-type AccountData = {
-    account_address: string;
-    public_key: string;
-    private_key: string;
-    balance: string;
-};
+function postJson(port: number, path: string, body: unknown): Promise<any> {
+    return new Promise((resolve, reject) => {
+        const request = http.request(
+            { host: '127.0.0.1', port, path, method: 'POST', headers: { 'Content-Type': 'application/json' } },
+            (response) => {
+                let data = '';
+                response.on('data', (chunk) => (data += chunk));
+                response.on('end', () => resolve(data ? JSON.parse(data) : undefined));
+            }
+        );
+        request.on('error', reject);
+        request.end(JSON.stringify(body));
+    });
+}
+
+function rpcCall(port: number, method: string, params: unknown = []): Promise<any> {
+    return postJson(port, '/rpc', { jsonrpc: '2.0', id: 1, method, params });
+}
+
+function waitFor(condition: () => boolean, timeoutMs = 5000): Promise<void> {
+    const started = Date.now();
+    return new Promise((resolve, reject) => {
+        const check = () => {
+            if (condition()) {
+                resolve();
+            } else if (Date.now() - started > timeoutMs) {
+                reject(new Error('Timed out waiting for condition'));
+            } else {
+                setTimeout(check, 10);
+            }
+        };
+        check();
+    });
+}
 
-function dataFeed(val: string): void {
-    console.log('hehe', val);
+function dataFeed(error: Error | undefined, event?: FeedEvent): void {
+    console.log('hehe', error, event);
+}
+
+function collectFeed(events: FeedEvent[], errors: Error[] = []): ProviderCallback {
+    return (error, event) => {
+        if (error) {
+            errors.push(error);
+        } else {
+            events.push(event!);
+        }
+    };
+}
+
+function blockNumbers(events: FeedEvent[]): number[] {
+    return events.flatMap((event) => (event.type == 'NewBlock' ? [event.block.block_number] : []));
 }
 
 describe('Alpaca-addon', function () {
@@ -20,8 +67,8 @@ describe('Alpaca-addon', function () {
             totalAccounts: 2,
         };
 
-        let res = await Devnet.start(config, dataFeed);
-        let accountData: AccountData[] = res.map((el) => el as unknown as AccountData);
+        let devnet = await Devnet.start(config, dataFeed);
+        let accountData: AccountData[] = devnet.accounts;
         expect(accountData[0]).to.deep.equal({
             account_address: '0x1c12570d28567eeb3b5323dc3cc6ccb9a094b809c8e616c8a13f34c258736c8',
             public_key: '0x2ccb5e686566bc0b13d6c48bffb9cd442f1e2b34aab6e3b287a30d0c9c5432e',
@@ -35,6 +82,8 @@ describe('Alpaca-addon', function () {
             private_key: '0x644188b5afe811fe97dbc7ee8393047a',
             balance: '0x3635c9adc5dea00000',
         });
+
+        await devnet.stop();
     });
 
     it('Same endpoint error', async function () {
@@ -44,8 +93,8 @@ describe('Alpaca-addon', function () {
             totalAccounts: 2,
         };
 
+        let devnet = await Devnet.start(config, dataFeed);
         try {
-            // TODO: make shutdown/stop method on Devnet
             await Devnet.start(config, dataFeed);
             expect.fail('Should of received an error');
         } catch (anyErr: unknown) {
@@ -54,6 +103,660 @@ describe('Alpaca-addon', function () {
             expect(err.type).to.eq(1);
             expect(err.engine).to.eq('alpaca-addon');
             expect(err.backtrace).to.be.not.empty
+        } finally {
+            await devnet.stop();
+        }
+    });
+
+    it('Port is released after stop', async function () {
+        let config: DevnetConfig = {
+            seed: 20,
+            port: 5050,
+            totalAccounts: 1,
+        };
+
+        let devnet = await Devnet.start(config, dataFeed);
+        await devnet.stop();
+        // Stopping twice is a no-op
+        await devnet.stop();
+
+        devnet = await Devnet.start(config, dataFeed);
+        await devnet.stop();
+    });
+
+    it('Optional config fields', async function () {
+        let config: DevnetConfig = {
+            seed: 20,
+            port: 5050,
+            totalAccounts: 1,
+            host: '127.0.0.1',
+            chainId: 'TESTNET',
+            initialBalance: '0x100',
+            startTime: 1700000000,
+        };
+
+        let devnet = await Devnet.start(config, dataFeed);
+        expect(devnet.accounts[0].balance).to.eq('0x100');
+        await devnet.stop();
+    });
+
+    it('Invalid config fields are reported together', async function () {
+        let config = {
+            seed: -1,
+            port: 70000,
+            totalAccounts: 300,
+            gasPrice: NaN,
+            chainId: 'GOERLI',
+        } as unknown as DevnetConfig;
+
+        try {
+            await Devnet.start(config, dataFeed);
+            expect.fail('Should of received an error');
+        } catch (anyErr: unknown) {
+            let err = anyErr as unknown as Error;
+            expect(err.type).to.eq(ErrorType.ConfigError);
+            expect(err.engine).to.eq('alpaca-addon');
+            expect(err.fields!.map((el) => el.field)).to.deep.equal(['seed', 'totalAccounts', 'port', 'chainId', 'gasPrice']);
+        }
+    });
+
+    it('Missing required config field', async function () {
+        let config = { seed: 20, totalAccounts: 1.5 } as unknown as DevnetConfig;
+
+        try {
+            await Devnet.start(config, dataFeed);
+            expect.fail('Should of received an error');
+        } catch (anyErr: unknown) {
+            let err = anyErr as unknown as Error;
+            expect(err.type).to.eq(ErrorType.ConfigError);
+            expect(err.fields).to.deep.equal([
+                { field: 'totalAccounts', message: 'expected an integer, got 1.5' },
+                { field: 'port', message: 'is required' },
+            ]);
+        }
+    });
+
+    it('Unreadable account class is reported as a config field', async function () {
+        let config: DevnetConfig = { seed: 20, port: 5050, totalAccounts: 1, accountClassPath: '/nonexistent/account.json' };
+
+        try {
+            await Devnet.start(config, dataFeed);
+            expect.fail('Should of received an error');
+        } catch (anyErr: unknown) {
+            let err = anyErr as unknown as Error;
+            expect(err.type).to.eq(ErrorType.ConfigError);
+            expect(err.fields!.map((el) => el.field)).to.deep.equal(['accountClassPath']);
+            expect(err.fields![0].message).to.match(/^can't be read/);
+        }
+    });
+
+    it('Bad arguments reject the returned promise', async function () {
+        // The raw addon functions, so that arguments can be missing or of the wrong type
+        const addon = require('alpaca-addon/getAlpaca');
+        const calls: (() => Promise<unknown>)[] = [
+            () => addon.createDevnetServer(),
+            () => addon.createDevnetServer({ seed: 20, port: 5050, totalAccounts: 1 }, 'not a function'),
+            () => addon.stopDevnetServer(),
+            () => addon.stopDevnetServer({ id: 1 }),
+        ];
+
+        for (const call of calls) {
+            const result = call();
+            expect(result).to.be.an.instanceOf(Promise);
+
+            try {
+                await result;
+                expect.fail('Should of received an error');
+            } catch (anyErr: unknown) {
+                let err = anyErr as unknown as Error;
+                expect(err.type).to.eq(ErrorType.ArgumentError);
+                expect(err.engine).to.eq('alpaca-addon');
+                expect(err.backtrace).to.be.not.empty;
+            }
+        }
+    });
+
+    it('Data feed delivers every new block in order', async function () {
+        let config: DevnetConfig = {
+            seed: 20,
+            port: 5050,
+            totalAccounts: 1,
+        };
+
+        let events: FeedEvent[] = [];
+        let devnet = await Devnet.start(config, collectFeed(events));
+
+        await postJson(config.port, '/create_block', {});
+        await postJson(config.port, '/create_block', {});
+        await rpcCall(config.port, 'starknet_blockNumber');
+
+        await waitFor(() => events.length == 3);
+        expect(blockNumbers(events)).to.deep.equal([0, 1, 2]);
+
+        await devnet.stop();
+    });
+
+    it('Data feed reports blocks created through the HTTP API', async function () {
+        let config: DevnetConfig = {
+            seed: 20,
+            port: 5050,
+            totalAccounts: 1,
+        };
+
+        let events: FeedEvent[] = [];
+        let devnet = await Devnet.start(config, collectFeed(events));
+
+        // No JSON-RPC request is made, the admin route alone has to trigger the feed
+        await postJson(config.port, '/create_block', {});
+        await postJson(config.port, '/mint', { address: devnet.accounts[0].account_address, amount: 1000 });
+
+        await waitFor(() => events.length == 3);
+        expect(blockNumbers(events)).to.deep.equal([0, 1, 2]);
+
+        await devnet.stop();
+    });
+
+    it('Data feed reports aborted blocks', async function () {
+        let config: DevnetConfig = {
+            seed: 20,
+            port: 5050,
+            totalAccounts: 1,
+        };
+
+        let events: any[] = [];
+        let devnet = await Devnet.start(config, collectFeed(events));
+
+        await postJson(config.port, '/create_block', {});
+        await postJson(config.port, '/create_block', {});
+        await waitFor(() => events.length == 3);
+
+        await postJson(config.port, '/abort_blocks', { starting_block_hash: events[1].block.block_hash });
+        await postJson(config.port, '/create_block', {});
+        await waitFor(() => events.length == 5);
+
+        expect(events[3]).to.deep.equal({
+            seq: 4,
+            type: 'BlocksAborted',
+            blocks: [
+                { block_number: 1, block_hash: events[1].block.block_hash },
+                { block_number: 2, block_hash: events[2].block.block_hash },
+            ],
+        });
+        expect(events[4].type).to.eq('NewBlock');
+        expect(events[4].block.block_number).to.eq(1);
+        expect(events[4].block.block_hash).to.not.eq(events[1].block.block_hash);
+
+        await devnet.stop();
+    });
+
+    it('Data feed resets on restart', async function () {
+        let config: DevnetConfig = {
+            seed: 20,
+            port: 5050,
+            totalAccounts: 1,
+        };
+
+        let events: FeedEvent[] = [];
+        let devnet = await Devnet.start(config, collectFeed(events));
+
+        await postJson(config.port, '/create_block', {});
+        await waitFor(() => events.length == 2);
+
+        await postJson(config.port, '/restart', {});
+        await waitFor(() => events.length == 4);
+
+        expect(events[2]).to.deep.equal({ seq: 3, type: 'ChainReset', reason: 'restart', accounts: devnet.accounts });
+        expect(blockNumbers(events.slice(3))).to.deep.equal([0]);
+
+        await devnet.stop();
+        expect(events[events.length - 1]).to.deep.equal({ seq: events.length, type: 'ServerStopped' });
+    });
+
+    it('Data feed emits the genesis block on startup', async function () {
+        let config: DevnetConfig = {
+            seed: 20,
+            port: 5050,
+            totalAccounts: 1,
+        };
+
+        let events: FeedEvent[] = [];
+        let devnet = await Devnet.start(config, collectFeed(events));
+
+        await waitFor(() => events.length == 1);
+        expect(blockNumbers(events)).to.deep.equal([0]);
+
+        await devnet.stop();
+    });
+
+    it('Data feed keeps order under a burst of blocks', async function () {
+        let config: DevnetConfig = {
+            seed: 20,
+            port: 5050,
+            totalAccounts: 1,
+        };
+
+        let events: FeedEvent[] = [];
+        let devnet = await Devnet.start(config, collectFeed(events));
+
+        const total = 100;
+        await Promise.all([...Array(total)].map(() => postJson(config.port, '/create_block', {})));
+
+        await waitFor(() => events.length == total + 1);
+        expect(blockNumbers(events)).to.deep.equal([...Array(total + 1).keys()]);
+        expect(devnet.feedQueueDepth).to.eq(0);
+
+        await devnet.stop();
+    });
+
+    it('Data feed events are sequenced and can be replayed', async function () {
+        let config: DevnetConfig = {
+            seed: 20,
+            port: 5050,
+            totalAccounts: 1,
+        };
+
+        let events: FeedEvent[] = [];
+        let devnet = await Devnet.start(config, collectFeed(events));
+
+        await postJson(config.port, '/create_block', {});
+        await postJson(config.port, '/create_block', {});
+        await waitFor(() => events.length == 3);
+
+        expect(events.map((event) => event.seq)).to.deep.equal([1, 2, 3]);
+        expect(devnet.getFeedCursor()).to.eq(3);
+        expect(devnet.replayFrom(2)).to.deep.equal(events.slice(1));
+        expect(devnet.replayFrom(4)).to.deep.equal([]);
+        expect(() => devnet.replayFrom(-1))
+            .to.throw()
+            .that.includes({ type: ErrorType.ArgumentError, engine: 'alpaca-addon' });
+
+        await devnet.stop();
+    });
+
+    it('Data feed keeps only the configured number of events for replay', async function () {
+        let config: DevnetConfig = {
+            seed: 20,
+            port: 5050,
+            totalAccounts: 1,
+            feedReplayCapacity: 2,
+        };
+
+        let events: FeedEvent[] = [];
+        let devnet = await Devnet.start(config, collectFeed(events));
+
+        await postJson(config.port, '/create_block', {});
+        await postJson(config.port, '/create_block', {});
+        await waitFor(() => events.length == 3);
+
+        expect(devnet.replayFrom(2)).to.deep.equal(events.slice(1));
+        expect(() => devnet.replayFrom(1)).to.throw();
+
+        await devnet.stop();
+    });
+
+    it('Data feed sheds blocks when the queue limit is reached', async function () {
+        let config: DevnetConfig = {
+            seed: 20,
+            port: 5050,
+            totalAccounts: 1,
+            feedQueueLimit: 1,
+            feedOverflow: 'coalesce',
+        };
+
+        let events: FeedEvent[] = [];
+        let collect = collectFeed(events);
+        let devnet = await Devnet.start(config, (error, event) => {
+            // Slow consumer, so that the queue fills up
+            const started = Date.now();
+            while (Date.now() - started < 20) {}
+            collect(error, event);
+        });
+
+        const total = 50;
+        await Promise.all([...Array(total)].map(() => postJson(config.port, '/create_block', {})));
+        await waitFor(() => blockNumbers(events).includes(total));
+
+        const seqs = events.map((event) => event.seq);
+        expect(seqs).to.deep.equal([...seqs].sort((a, b) => a - b));
+
+        const lagged = events.filter((event) => event.type == 'Lagged');
+        expect(lagged).to.not.be.empty;
+        for (const event of lagged) {
+            if (event.type == 'Lagged') {
+                const last = event.last_skipped_seq;
+                const skipped = devnet.replayFrom(event.first_skipped_seq).filter((replayed) => replayed.seq <= last);
+                expect(blockNumbers(skipped)).to.have.length(event.skipped);
+            }
+        }
+
+        await devnet.stop();
+    });
+
+    it('Data feed delivers pre-serialized payloads', async function () {
+        for (const feedFormat of ['json', 'buffer'] as const) {
+            let config: DevnetConfig = {
+                seed: 20,
+                port: 5050,
+                totalAccounts: 1,
+                feedFormat,
+            };
+
+            let payloads: (string | Buffer)[] = [];
+            let devnet = await Devnet.start(config, (error: Error | undefined, payload?: string | Buffer) => {
+                expect(error).to.be.undefined;
+                payloads.push(payload!);
+            });
+
+            await postJson(config.port, '/create_block', {});
+            await waitFor(() => payloads.length == 2);
+            await devnet.stop();
+
+            for (const payload of payloads) {
+                expect(feedFormat == 'json' ? typeof payload == 'string' : Buffer.isBuffer(payload)).to.be.true;
+            }
+            const events: FeedEvent[] = payloads.map((payload) => JSON.parse(payload.toString()));
+            expect(events).to.deep.equal(devnet.replayFrom(1).slice(0, 2));
+            expect(blockNumbers(events)).to.deep.equal([0, 1]);
+        }
+    });
+
+    it('Registry lists running instances and stops them all', async function () {
+        let first = await Devnet.start({ seed: 20, port: 5050, totalAccounts: 1 }, dataFeed);
+        // Properties that aren't config fields are neither validated nor listed
+        const secondConfig = { seed: 21, port: 5051, totalAccounts: 2, onReady: () => {} } as DevnetConfig;
+        let second = await Devnet.start(secondConfig, dataFeed);
+
+        expect(first.id).to.not.eq(second.id);
+        expect(Devnet.list()).to.deep.equal([
+            { id: first.id, port: 5050, config: { seed: 20, port: 5050, totalAccounts: 1 }, status: 'running' },
+            { id: second.id, port: 5051, config: { seed: 21, port: 5051, totalAccounts: 2 }, status: 'running' },
+        ]);
+        expect(Devnet.get(second.id)?.accounts).to.deep.equal(second.accounts);
+
+        await Devnet.stopAll();
+        expect(Devnet.list()).to.be.empty;
+        expect(Devnet.get(first.id)).to.be.undefined;
+    });
+
+    it('Instances are stopped when the environment that started them is torn down', async function () {
+        let devnet = await Devnet.start({ seed: 20, port: 5051, totalAccounts: 1 }, dataFeed);
+
+        // Terminating the worker tears its environment down without the devnet being stopped
+        const worker = new Worker(
+            `const { Devnet } = require(${JSON.stringify(require.resolve('alpaca-addon'))});
+            Devnet.start({ seed: 20, port: 5050, totalAccounts: 1 }, () => {})
+                .then((devnet) => require('worker_threads').parentPort.postMessage(devnet.id));`,
+            { eval: true }
+        );
+        const id = await new Promise<number>((resolve, reject) => {
+            worker.once('message', resolve);
+            worker.once('error', reject);
+        });
+        expect(Devnet.list().map((info) => info.id)).to.include(id);
+
+        await worker.terminate();
+        // Shut down in the background, the port is released once it's gone from the registry
+        await waitFor(() => !Devnet.list().some((info) => info.id == id));
+
+        // Instances of other environments keep running
+        expect(Devnet.list().map((info) => info.id)).to.deep.equal([devnet.id]);
+        await devnet.stop();
+
+        devnet = await Devnet.start({ seed: 20, port: 5050, totalAccounts: 1 }, dataFeed);
+        await devnet.stop();
+    });
+
+    it('Instances that dump on exit have dumped by the time their environment is torn down', async function () {
+        const dumpPath = path.join(os.tmpdir(), `alpaca-dump-on-exit-${process.pid}.json`);
+        fs.rmSync(dumpPath, { force: true });
+
+        const config: DevnetConfig = { seed: 20, port: 0, totalAccounts: 1, headless: true, dumpOn: 'exit', dumpPath };
+        const worker = new Worker(
+            `const { Devnet } = require(${JSON.stringify(require.resolve('alpaca-addon'))});
+            Devnet.start(${JSON.stringify(config)}, () => {})
+                .then((devnet) => devnet.createBlock())
+                .then(() => require('worker_threads').parentPort.postMessage('ready'));`,
+            { eval: true }
+        );
+        await new Promise((resolve, reject) => {
+            worker.once('message', resolve);
+            worker.once('error', reject);
+        });
+
+        try {
+            await worker.terminate();
+            expect(fs.existsSync(dumpPath)).to.be.true;
+        } finally {
+            fs.rmSync(dumpPath, { force: true });
+        }
+    });
+
+    it('Port 0 binds to a free port', async function () {
+        let first = await Devnet.start({ seed: 20, port: 0, totalAccounts: 1 }, dataFeed);
+        let second = await Devnet.start({ seed: 20, port: 0, totalAccounts: 1 }, dataFeed);
+
+        try {
+            expect(first.port).to.not.eq(0);
+            expect(first.port).to.not.eq(second.port);
+            expect(first.url).to.eq(`http://127.0.0.1:${first.port}`);
+            expect(first.address).to.eq(`127.0.0.1:${first.port}`);
+            expect(Devnet.list().map((info) => info.port)).to.deep.equal([first.port, second.port]);
+
+            const response = await rpcCall(second.port!, 'starknet_blockNumber');
+            expect(response.result).to.eq(0);
+        } finally {
+            await Devnet.stopAll();
+        }
+    });
+
+    it('Auto port falls back to the next free port', async function () {
+        let busy = await Devnet.start({ seed: 20, port: 5050, totalAccounts: 1 }, dataFeed);
+
+        try {
+            let devnet = await Devnet.start({ seed: 20, port: 5050, autoPort: 2, totalAccounts: 1 }, dataFeed);
+            expect(devnet.port).to.eq(5051);
+
+            try {
+                await Devnet.start({ seed: 20, port: 5050, autoPort: 1, totalAccounts: 1 }, dataFeed);
+                expect.fail('Should of received an error');
+            } catch (anyErr: unknown) {
+                let err = anyErr as unknown as Error;
+                expect(err.type).to.eq(ErrorType.DevnetError);
+                expect(err.attempts!.map((attempt) => attempt.port)).to.deep.equal([5050, 5051]);
+            }
+
+            // Only busy ports are skipped, an address that can't be bound at all fails right away
+            try {
+                await Devnet.start({ seed: 20, port: 5050, autoPort: 2, host: '203.0.113.1', totalAccounts: 1 }, dataFeed);
+                expect.fail('Should of received an error');
+            } catch (anyErr: unknown) {
+                let err = anyErr as unknown as Error;
+                expect(err.type).to.eq(ErrorType.DevnetError);
+                expect(err.attempts).to.be.undefined;
+            }
+        } finally {
+            await Devnet.stopAll();
+        }
+    });
+
+    it('In-process JSON-RPC requests', async function () {
+        let devnet = await Devnet.start({ seed: 20, port: 0, totalAccounts: 1 }, dataFeed);
+
+        try {
+            expect(await devnet.request('starknet_blockNumber')).to.eq(0);
+
+            const block = await devnet.request<any>('starknet_getBlockWithTxHashes', { block_id: 'latest' });
+            expect(block.block_number).to.eq(0);
+
+            try {
+                await devnet.request('starknet_noSuchMethod');
+                expect.fail('Should of received an error');
+            } catch (anyErr: unknown) {
+                let err = anyErr as unknown as Error;
+                expect(err.type).to.eq(ErrorType.DevnetError);
+                expect(err.code).to.eq(-32601);
+            }
+        } finally {
+            await devnet.stop();
+        }
+    });
+
+    it('Headless devnet is only reachable in-process', async function () {
+        let events: FeedEvent[] = [];
+        let first = await Devnet.start({ seed: 20, port: 5050, totalAccounts: 1, headless: true }, collectFeed(events));
+        let second = await Devnet.start({ seed: 20, port: 5050, totalAccounts: 1, headless: true }, dataFeed);
+
+        try {
+            expect(first.url).to.be.undefined;
+            expect(first.port).to.be.undefined;
+            expect(Devnet.list().map((info) => info.port)).to.deep.equal([null, null]);
+
+            expect(await first.request('starknet_blockNumber')).to.eq(0);
+            expect(await second.request('starknet_blockNumber')).to.eq(0);
+            await waitFor(() => events.length == 1);
+            expect(blockNumbers(events)).to.deep.equal([0]);
+        } finally {
+            await Devnet.stopAll();
+        }
+
+        expect(events[events.length - 1].type).to.eq('ServerStopped');
+    });
+
+    it('Admin API calls skip HTTP and keep the data feed in sync', async function () {
+        let events: FeedEvent[] = [];
+        let devnet = await Devnet.start({ seed: 20, port: 0, totalAccounts: 1, headless: true }, collectFeed(events));
+
+        try {
+            const address = devnet.accounts[0].account_address;
+            const minted = await devnet.mint({ address, amount: '1000' });
+            expect(minted.tx_hash).to.be.a('string');
+
+            const { block_hash } = await devnet.createBlock();
+            await waitFor(() => events.length == 3);
+            expect(blockNumbers(events)).to.deep.equal([0, 1, 2]);
+            expect(events[2].type == 'NewBlock' && events[2].block.block_hash).to.eq(block_hash);
+
+            const balance = await devnet.accountBalance({ address });
+            expect(balance.amount).to.eq(minted.new_balance);
+
+            // Beyond Number.MAX_SAFE_INTEGER, which is common for WEI
+            const large = 2n ** 70n;
+            const mintedLarge = await devnet.mint({ address, amount: large });
+            expect(BigInt(mintedLarge.new_balance)).to.eq(BigInt(balance.amount) + large);
+
+            await devnet.restart();
+            await waitFor(() => events.some((event) => event.type == 'ChainReset'));
+
+            try {
+                await devnet.abortBlocks({ starting_block_hash: '0x1' });
+                expect.fail('Should of received an error');
+            } catch (anyErr: unknown) {
+                let err = anyErr as unknown as Error;
+                expect(err.type).to.eq(ErrorType.DevnetError);
+                expect(err.status).to.be.at.least(400);
+            }
+        } finally {
+            await devnet.stop();
+        }
+    });
+
+    it('Blocks on demand keep transactions pending until a block is created', async function () {
+        let events: FeedEvent[] = [];
+        let devnet = await Devnet.start({ seed: 20, port: 0, totalAccounts: 1, headless: true, blocksOnDemand: true }, collectFeed(events));
+
+        try {
+            const address = devnet.accounts[0].account_address;
+            const first = await devnet.mint({ address, amount: '1000' });
+            const second = await devnet.mint({ address, amount: '1000' });
+
+            expect((await devnet.getBlock()).block_number).to.eq(0);
+            expect((await devnet.getTransactionReceipt(first.tx_hash)).block_number).to.be.undefined;
+
+            await devnet.createBlock();
+            await waitFor(() => events.length == 2);
+            expect(blockNumbers(events)).to.deep.equal([0, 1]);
+
+            const block = await devnet.getBlock();
+            expect(block.block_number).to.eq(1);
+            const hashes = block.transactions.map((transaction) => (transaction as { transaction_hash: string }).transaction_hash);
+            expect(hashes).to.deep.equal([first.tx_hash, second.tx_hash]);
+        } finally {
+            await devnet.stop();
+        }
+    });
+
+    it('Typed read API', async function () {
+        let devnet = await Devnet.start({ seed: 20, port: 0, totalAccounts: 1, headless: true }, dataFeed);
+
+        try {
+            const address = devnet.accounts[0].account_address;
+            const { tx_hash } = await devnet.mint({ address, amount: '1000' });
+
+            const block = await devnet.getBlock();
+            expect(block.block_number).to.eq(1);
+            expect((await devnet.getBlock({ block_number: 0 })).block_number).to.eq(0);
+
+            expect((await devnet.getTransaction(tx_hash)).transaction_hash).to.eq(tx_hash);
+            const receipt = await devnet.getTransactionReceipt(tx_hash);
+            expect(receipt.block_number).to.eq(1);
+            expect(receipt.execution_status).to.eq('SUCCEEDED');
+
+            const stateUpdate = await devnet.getStateUpdate({ block_hash: block.block_hash });
+            expect(stateUpdate.block_hash).to.eq(block.block_hash);
+
+            expect(await devnet.getNonce(address)).to.eq('0x0');
+
+            try {
+                await devnet.getTransaction('0x1');
+                expect.fail('Should of received an error');
+            } catch (anyErr: unknown) {
+                let err = anyErr as unknown as Error;
+                expect(err.type).to.eq(ErrorType.DevnetError);
+                expect(err.code).to.be.a('number');
+            }
+        } finally {
+            await devnet.stop();
+        }
+    });
+
+    it('Snapshot and revert devnet state', async function () {
+        let events: FeedEvent[] = [];
+        let devnet = await Devnet.start({ seed: 20, port: 0, totalAccounts: 1, headless: true }, collectFeed(events));
+
+        try {
+            const address = devnet.accounts[0].account_address;
+            await devnet.createBlock();
+            const balance = await devnet.accountBalance({ address });
+
+            const snapshot = await devnet.snapshot();
+            expect(snapshot.blockNumber).to.eq(1);
+            expect(await devnet.listSnapshots()).to.deep.equal([snapshot]);
+
+            await devnet.mint({ address, amount: '1000' });
+            await devnet.createBlock();
+            expect((await devnet.getBlock()).block_number).to.eq(3);
+
+            await devnet.revert(snapshot.id);
+            await waitFor(() => events.some((event) => event.type == 'ChainReset'));
+            const reset = events.find((event) => event.type == 'ChainReset');
+            expect(reset).to.deep.include({ type: 'ChainReset', reason: 'revert' });
+
+            expect((await devnet.getBlock()).block_number).to.eq(1);
+            expect((await devnet.accountBalance({ address })).amount).to.eq(balance.amount);
+
+            // Kept for further reverts until deleted
+            await devnet.deleteSnapshot(snapshot.id);
+            expect(await devnet.listSnapshots()).to.deep.equal([]);
+
+            try {
+                await devnet.revert(snapshot.id);
+                expect.fail('Should of received an error');
+            } catch (anyErr: unknown) {
+                let err = anyErr as unknown as Error;
+                expect(err.type).to.eq(ErrorType.DevnetError);
+            }
+        } finally {
+            await devnet.stop();
         }
     });
 });